@@ -1,21 +1,170 @@
-use std::fs;
 use itertools::izip;
-use regex:: Regex;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::{self, BufRead, BufReader};
+use std::iter::repeat_n;
 
-fn build_lists(input: &str) -> (Vec<isize>, Vec<isize>) {
-    let mut lefts: Vec<isize> = Vec::new();
-    let mut rights: Vec<isize> = Vec::new();
-    let re = Regex::new(r"(\d+)\s+(\d+)").unwrap();
-    for line in input.lines() {
-        let caps = re.captures(line).unwrap();
-        lefts.push(caps[1].parse().unwrap());
-        rights.push(caps[2].parse().unwrap());
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum ColumnError {
+    Number {
+        line: usize,
+        token: String,
+    },
+    Ragged {
+        line: usize,
+        expected: usize,
+        found: usize,
+    },
+}
+
+impl fmt::Display for ColumnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ColumnError::Number { line, token } => {
+                write!(f, "line {}: {:?} is not a number", line, token)
+            }
+            ColumnError::Ragged {
+                line,
+                expected,
+                found,
+            } => write!(
+                f,
+                "line {}: expected {} columns, found {}",
+                line, expected, found
+            ),
+        }
     }
-    (lefts, rights)
 }
 
-fn part1(input: &str) -> usize {
-    let (mut lefts, mut rights) = build_lists(input);
+impl Error for ColumnError {}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct ListStats {
+    lefts: BTreeMap<isize, usize>,
+    rights: BTreeMap<isize, usize>,
+    len: usize,
+}
+
+impl ListStats {
+    fn from_reader(reader: impl BufRead) -> io::Result<ListStats> {
+        let mut stats = ListStats::default();
+        for (idx, line) in reader.lines().enumerate() {
+            let row = parse_row(idx + 1, &line?)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+            match row[..] {
+                [] => {}
+                [left, right] => stats.push(left, right),
+                _ => {
+                    let err = ColumnError::Ragged {
+                        line: idx + 1,
+                        expected: 2,
+                        found: row.len(),
+                    };
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, err));
+                }
+            }
+        }
+        Ok(stats)
+    }
+
+    fn push(&mut self, left: isize, right: isize) {
+        *self.lefts.entry(left).or_default() += 1;
+        *self.rights.entry(right).or_default() += 1;
+        self.len += 1;
+    }
+
+    fn expand(counts: &BTreeMap<isize, usize>) -> impl Iterator<Item = isize> + '_ {
+        counts
+            .iter()
+            .flat_map(|(&value, &count)| repeat_n(value, count))
+    }
+
+    fn distance(&self) -> usize {
+        Self::expand(&self.lefts)
+            .zip(Self::expand(&self.rights))
+            .map(|(left, right)| left.abs_diff(right))
+            .sum()
+    }
+
+    fn similarity(&self) -> isize {
+        self.lefts
+            .iter()
+            .map(|(&value, &count)| {
+                value * (count * self.rights.get(&value).unwrap_or(&0)) as isize
+            })
+            .sum()
+    }
+
+    fn median(counts: &BTreeMap<isize, usize>) -> Option<f64> {
+        let len: usize = counts.values().sum();
+        if len == 0 {
+            return None;
+        }
+        let mut middle = Self::expand(counts).skip((len - 1) / 2);
+        let low = middle.next()? as f64;
+        if len % 2 == 1 {
+            Some(low)
+        } else {
+            Some((low + middle.next()? as f64) / 2.0)
+        }
+    }
+
+    fn duplicates(counts: &BTreeMap<isize, usize>) -> usize {
+        counts.values().filter(|&&count| count > 1).count()
+    }
+}
+
+fn parse_row(line: usize, text: &str) -> Result<Vec<isize>, ColumnError> {
+    text.split_whitespace()
+        .map(|token| {
+            token.parse().map_err(|_| ColumnError::Number {
+                line,
+                token: token.to_string(),
+            })
+        })
+        .collect()
+}
+
+fn build_columns(input: &str) -> Result<Vec<Vec<isize>>, ColumnError> {
+    let mut columns: Vec<Vec<isize>> = Vec::new();
+    for (idx, line) in input.lines().enumerate() {
+        let row = parse_row(idx + 1, line)?;
+        if row.is_empty() {
+            continue;
+        }
+        if columns.is_empty() {
+            columns = vec![Vec::new(); row.len()];
+        }
+        if row.len() != columns.len() {
+            return Err(ColumnError::Ragged {
+                line: idx + 1,
+                expected: columns.len(),
+                found: row.len(),
+            });
+        }
+        for (column, value) in columns.iter_mut().zip(row) {
+            column.push(value);
+        }
+    }
+    Ok(columns)
+}
+
+fn build_lists(input: &str) -> (Vec<isize>, Vec<isize>) {
+    let mut columns = build_columns(input)
+        .expect("input should have even columns")
+        .into_iter();
+    (
+        columns.next().unwrap_or_default(),
+        columns.next().unwrap_or_default(),
+    )
+}
+
+fn distance(lefts: &[isize], rights: &[isize]) -> usize {
+    let mut lefts = lefts.to_vec();
+    let mut rights = rights.to_vec();
     let mut total = 0;
     lefts.sort();
     rights.sort();
@@ -25,17 +174,86 @@ fn part1(input: &str) -> usize {
     total
 }
 
-fn part2(input: &str) -> usize {
-    let (lefts, rights) = build_lists(input);
+fn histogram(values: &[isize]) -> HashMap<isize, usize> {
+    let mut counts = HashMap::new();
+    for value in values {
+        *counts.entry(*value).or_default() += 1;
+    }
+    counts
+}
+
+fn similarity(lefts: &[isize], rights: &[isize]) -> isize {
+    let counts = histogram(rights);
     let mut total = 0;
     for item in lefts {
-        total += item as usize * rights.iter().filter(|x| **x == item).count()
+        total += item * *counts.get(item).unwrap_or(&0) as isize
     }
     total
 }
 
+fn part1(input: &str) -> usize {
+    let (lefts, rights) = build_lists(input);
+    distance(&lefts, &rights)
+}
+
+fn part2(input: &str) -> isize {
+    let (lefts, rights) = build_lists(input);
+    similarity(&lefts, &rights)
+}
+
 fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args
+        .iter()
+        .any(|arg| arg == "--stats" || arg == "--histogram")
+    {
+        let file = fs::File::open("input.txt").expect("input.txt should exist");
+        let stats =
+            ListStats::from_reader(BufReader::new(file)).expect("input.txt should be readable");
+        println!("pairs: {}", stats.len);
+        println!("distance: {}", stats.distance());
+        println!("similarity: {}", stats.similarity());
+        println!(
+            "median: {:?} {:?}",
+            ListStats::median(&stats.lefts),
+            ListStats::median(&stats.rights)
+        );
+        println!(
+            "duplicates: {} {}",
+            ListStats::duplicates(&stats.lefts),
+            ListStats::duplicates(&stats.rights)
+        );
+        if args.iter().any(|arg| arg == "--histogram") {
+            let values: BTreeSet<&isize> = stats.lefts.keys().chain(stats.rights.keys()).collect();
+            for value in values {
+                println!(
+                    "{}: {} {}",
+                    value,
+                    stats.lefts.get(value).unwrap_or(&0),
+                    stats.rights.get(value).unwrap_or(&0)
+                );
+            }
+        }
+        return;
+    }
     let input = fs::read_to_string("input.txt").expect("input.txt should exist");
+    if let Some(pair) = args
+        .iter()
+        .position(|arg| arg == "--columns")
+        .and_then(|idx| args.get(idx + 1))
+    {
+        let (a, b) = pair.split_once(',').expect("columns should be a,b");
+        let (a, b): (usize, usize) = (a.parse().unwrap(), b.parse().unwrap());
+        match build_columns(&input) {
+            Ok(columns) if a < columns.len() && b < columns.len() => {
+                println!("distance: {}", distance(&columns[a], &columns[b]));
+                println!("similarity: {}", similarity(&columns[a], &columns[b]));
+            }
+            Ok(columns) => eprintln!("input only has {} columns", columns.len()),
+            Err(err) => eprintln!("{}", err),
+        }
+        return;
+    }
     println!("{}", part1(&input));
     println!("{}", part2(&input));
 }
@@ -55,4 +273,51 @@ mod tests {
         let input = fs::read_to_string("test.txt").expect("test.txt should exist");
         assert_eq!(31, part2(&input));
     }
+
+    #[test]
+    fn test_stats() {
+        let file = fs::File::open("test.txt").expect("test.txt should exist");
+        let stats = ListStats::from_reader(BufReader::new(file)).unwrap();
+        assert_eq!(6, stats.len);
+        assert_eq!(11, stats.distance());
+        assert_eq!(31, stats.similarity());
+        assert_eq!(Some(3.0), ListStats::median(&stats.lefts));
+        assert_eq!(Some(3.5), ListStats::median(&stats.rights));
+        assert_eq!(1, ListStats::duplicates(&stats.lefts));
+        assert_eq!(Some(&3), stats.rights.get(&3));
+        assert_eq!(None, ListStats::median(&BTreeMap::new()));
+
+        let stats = ListStats::from_reader("-3 -3\n4 4\n".as_bytes()).unwrap();
+        assert_eq!(1, stats.similarity());
+        let stats = ListStats::from_reader("-3 -3\n-3 4\n".as_bytes()).unwrap();
+        assert_eq!(-6, stats.similarity());
+        assert_eq!(-6, part2("-3 -3\n-3 4\n"));
+    }
+
+    #[test]
+    fn test_columns() {
+        let columns = build_columns("3 -4 1\n-2 5 1\n\n7 0 -1\n").unwrap();
+        assert_eq!(
+            vec![vec![3, -2, 7], vec![-4, 5, 0], vec![1, 1, -1]],
+            columns
+        );
+        assert_eq!(9, distance(&columns[0], &columns[2]));
+        assert_eq!(3, similarity(&columns[2], &columns[2]));
+        assert_eq!(0, similarity(&columns[0], &columns[1]));
+        assert_eq!(
+            Err(ColumnError::Ragged {
+                line: 2,
+                expected: 2,
+                found: 3
+            }),
+            build_columns("1 2\n3 4 5\n")
+        );
+        assert_eq!(
+            Err(ColumnError::Number {
+                line: 1,
+                token: "x".to_string()
+            }),
+            build_columns("1 x\n")
+        );
+    }
 }
@@ -1,17 +1,120 @@
-use pathfinding::prelude::yen;
-use std::collections::HashSet;
+use std::collections::{HashSet, VecDeque};
+use std::env;
 use std::fs;
 
+#[derive(Clone, Debug, Default)]
+struct Reach {
+    summits: HashSet<(usize, usize)>,
+    trails: usize,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Neighbourhood {
+    Four,
+    Eight,
+}
+
+impl Neighbourhood {
+    fn offsets(&self) -> &'static [(isize, isize)] {
+        match self {
+            Neighbourhood::Four => &[(-1, 0), (0, -1), (1, 0), (0, 1)],
+            Neighbourhood::Eight => &[
+                (-1, -1),
+                (0, -1),
+                (1, -1),
+                (-1, 0),
+                (1, 0),
+                (-1, 1),
+                (0, 1),
+                (1, 1),
+            ],
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Rules {
+    step: fn(usize, usize) -> bool,
+    start: usize,
+    goal: usize,
+    neighbourhood: Neighbourhood,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules {
+            step: |current, next| next == current + 1,
+            start: 0,
+            goal: 9,
+            neighbourhood: Neighbourhood::Four,
+        }
+    }
+}
+
+impl Rules {
+    fn descending() -> Self {
+        Rules {
+            step: |current, next| current == next + 1,
+            start: 9,
+            goal: 0,
+            ..Rules::default()
+        }
+    }
+}
+
 struct Topology {
-    map: Vec<Vec<usize>>,
+    map: Vec<Vec<Option<usize>>>,
+    rules: Rules,
+}
+
+struct Trails<'a> {
+    topology: &'a Topology,
+    stack: Vec<Vec<(usize, usize)>>,
+}
+
+impl Iterator for Trails<'_> {
+    type Item = Vec<(usize, usize)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(path) = self.stack.pop() {
+            let &(x, y) = path.last().unwrap();
+            if self.topology.is_goal((x, y)) {
+                return Some(path);
+            }
+            for (next, _) in self.topology.successors((x, y)).into_iter().rev() {
+                if path.contains(&next) {
+                    continue;
+                }
+                let mut extended = path.clone();
+                extended.push(next);
+                self.stack.push(extended);
+            }
+        }
+        None
+    }
 }
 
 impl Topology {
+    fn with_rules(self, rules: Rules) -> Self {
+        Topology { rules, ..self }
+    }
+
+    fn height(&self, loc: (usize, usize)) -> Option<usize> {
+        self.map[loc.1][loc.0]
+    }
+
+    fn is_goal(&self, loc: (usize, usize)) -> bool {
+        self.height(loc) == Some(self.rules.goal)
+    }
+
     fn neighbours(&self, x: usize, y: usize) -> Vec<(usize, usize)> {
         let a = x as isize;
         let b = y as isize;
-        let ret = vec![(a - 1, b), (a, b - 1), (a + 1, b), (a, b + 1)];
-        ret.into_iter()
+        self.rules
+            .neighbourhood
+            .offsets()
+            .iter()
+            .map(|&(dx, dy)| (a + dx, b + dy))
             .filter(|&(x, y)| {
                 x >= 0
                     && y >= 0
@@ -25,66 +128,160 @@ impl Topology {
     fn successors(&self, loc: (usize, usize)) -> Vec<((usize, usize), usize)> {
         let x = loc.0;
         let y = loc.1;
-        let current = self.map[y][x];
         let mut ret = Vec::new();
-        for (x2, y2) in self.neighbours(x, y) {
-            if self.map[y2][x2] == current + 1 {
-                ret.push(((x2, y2), 1));
+        if let Some(current) = self.map[y][x] {
+            for (x2, y2) in self.neighbours(x, y) {
+                if let Some(next) = self.map[y2][x2] {
+                    if (self.rules.step)(current, next) {
+                        ret.push(((x2, y2), 1));
+                    }
+                }
             }
         }
         ret
     }
 
-    fn trails(&self, x: usize, y: usize) -> Vec<Vec<(usize, usize)>> {
-        yen(
-            &(x, y),
-            |x| self.successors(*x),
-            |&(x, y)| self.map[y][x] == 9,
-            100000,
-        )
-        .into_iter()
-        .map(|x| x.0)
-        .collect()
+    fn order(&self) -> Option<Vec<(usize, usize)>> {
+        let mut indegree: Vec<Vec<usize>> = self.map.iter().map(|row| vec![0; row.len()]).collect();
+        for y in 0..self.map.len() {
+            for x in 0..self.map[y].len() {
+                for ((x2, y2), _) in self.successors((x, y)) {
+                    indegree[y2][x2] += 1;
+                }
+            }
+        }
+        let mut queue = VecDeque::new();
+        for (y, row) in indegree.iter().enumerate() {
+            for (x, &count) in row.iter().enumerate() {
+                if count == 0 {
+                    queue.push_back((x, y));
+                }
+            }
+        }
+        let mut order = Vec::new();
+        while let Some(loc) = queue.pop_front() {
+            order.push(loc);
+            for ((x2, y2), _) in self.successors(loc) {
+                indegree[y2][x2] -= 1;
+                if indegree[y2][x2] == 0 {
+                    queue.push_back((x2, y2));
+                }
+            }
+        }
+        // Cells on a cycle never reach indegree zero, so a short order means
+        // the step rule is not acyclic and the DP below cannot be used.
+        let cells: usize = self.map.iter().map(|row| row.len()).sum();
+        if order.len() < cells {
+            None
+        } else {
+            Some(order)
+        }
     }
 
-    fn score(&self, x: usize, y: usize) -> usize {
-        if self.map[y][x] != 0 {
-            return 0;
+    fn reach_simple(&self) -> Vec<Vec<Reach>> {
+        // Counting simple paths is exponential, so only trailheads, the only
+        // cells callers read, are searched; the rest stay at the default.
+        let mut reach: Vec<Vec<Reach>> = self
+            .map
+            .iter()
+            .map(|row| vec![Reach::default(); row.len()])
+            .collect();
+        for (x, y) in self.trailheads() {
+            for trail in self.trails(x, y) {
+                reach[y][x].summits.insert(*trail.last().unwrap());
+                reach[y][x].trails += 1;
+            }
         }
-        let mut set = HashSet::new();
-        let paths = self.trails(x, y);
-        for path in paths {
-            set.insert(path.last().unwrap().clone());
+        reach
+    }
+
+    fn reach(&self) -> Vec<Vec<Reach>> {
+        let Some(order) = self.order() else {
+            return self.reach_simple();
+        };
+        let mut reach: Vec<Vec<Reach>> = self
+            .map
+            .iter()
+            .map(|row| vec![Reach::default(); row.len()])
+            .collect();
+        for (x, y) in order.into_iter().rev() {
+            let mut current = Reach::default();
+            if self.is_goal((x, y)) {
+                current.summits.insert((x, y));
+                current.trails = 1;
+            } else {
+                for ((x2, y2), _) in self.successors((x, y)) {
+                    let next = &reach[y2][x2];
+                    current.summits.extend(next.summits.iter().copied());
+                    current.trails += next.trails;
+                }
+            }
+            reach[y][x] = current;
         }
-        set.len()
+        reach
     }
 
-    fn score_all(&self) -> usize {
-        let mut sum = 0;
+    fn trailheads(&self) -> Vec<(usize, usize)> {
+        let mut heads = Vec::new();
         for y in 0..self.map.len() {
             for x in 0..self.map[y].len() {
-                sum += self.score(x, y);
+                if self.map[y][x] == Some(self.rules.start) {
+                    heads.push((x, y));
+                }
             }
         }
-        sum
+        heads
     }
 
-    fn rate(&self, x: usize, y: usize) -> usize {
-        if self.map[y][x] != 0 {
-            return 0;
+    fn trails(&self, x: usize, y: usize) -> Trails<'_> {
+        Trails {
+            topology: self,
+            stack: vec![vec![(x, y)]],
         }
-        let paths = self.trails(x, y);
-        paths.len()
     }
 
-    fn rate_all(&self) -> usize {
-        let mut sum = 0;
+    fn trailhead_summary(&self) -> Vec<((usize, usize), usize, usize)> {
+        let reach = self.reach();
+        self.trailheads()
+            .into_iter()
+            .map(|(x, y)| ((x, y), reach[y][x].summits.len(), reach[y][x].trails))
+            .collect()
+    }
+
+    fn to_dot(&self) -> String {
+        let mut res = String::from("digraph trails {\n");
         for y in 0..self.map.len() {
             for x in 0..self.map[y].len() {
-                sum += self.rate(x, y);
+                if let Some(height) = self.map[y][x] {
+                    res += &format!("    \"{},{}\" [label=\"{}\"];\n", x, y, height);
+                }
             }
         }
-        sum
+        for y in 0..self.map.len() {
+            for x in 0..self.map[y].len() {
+                for ((x2, y2), _) in self.successors((x, y)) {
+                    res += &format!("    \"{},{}\" -> \"{},{}\";\n", x, y, x2, y2);
+                }
+            }
+        }
+        res.push_str("}\n");
+        res
+    }
+
+    fn score_all(&self) -> usize {
+        let reach = self.reach();
+        self.trailheads()
+            .into_iter()
+            .map(|(x, y)| reach[y][x].summits.len())
+            .sum()
+    }
+
+    fn rate_all(&self) -> usize {
+        let reach = self.reach();
+        self.trailheads()
+            .into_iter()
+            .map(|(x, y)| reach[y][x].trails)
+            .sum()
     }
 }
 
@@ -94,11 +291,14 @@ impl From<&str> for Topology {
         for line in value.lines() {
             let mut row = Vec::new();
             for c in line.chars() {
-                row.push(c.to_digit(10).unwrap() as usize);
+                row.push(c.to_digit(10).map(|height| height as usize));
             }
             map.push(row);
         }
-        Topology { map }
+        Topology {
+            map,
+            rules: Rules::default(),
+        }
     }
 }
 
@@ -114,8 +314,46 @@ fn part2(input: &str) -> usize {
 
 fn main() {
     let input = fs::read_to_string("input.txt").expect("input.txt should exist");
-    println!("{}", part1(&input));
-    println!("{}", part2(&input));
+    let args: Vec<String> = env::args().collect();
+    let mut rules = if args.iter().any(|arg| arg == "--descending") {
+        Rules::descending()
+    } else {
+        Rules::default()
+    };
+    if args.iter().any(|arg| arg == "--eight") {
+        rules.neighbourhood = Neighbourhood::Eight;
+    }
+    let topology = Topology::from(&*input).with_rules(rules);
+    if args.iter().any(|arg| arg == "--trailheads") {
+        for ((x, y), score, rating) in topology.trailhead_summary() {
+            println!("{},{}: score {}, rating {}", x, y, score, rating);
+        }
+    }
+    if let Some(head) = args
+        .iter()
+        .position(|arg| arg == "--trails")
+        .and_then(|idx| args.get(idx + 1))
+    {
+        let (x, y) = head.split_once(',').expect("trailhead should be x,y");
+        let (x, y) = (x.parse().unwrap(), y.parse().unwrap());
+        for trail in topology.trails(x, y) {
+            let steps: Vec<String> = trail.iter().map(|(x, y)| format!("{},{}", x, y)).collect();
+            println!("{}", steps.join(" -> "));
+        }
+    }
+    if args.iter().any(|arg| arg == "--dot") {
+        print!("{}", topology.to_dot());
+    }
+    if args
+        .iter()
+        .any(|arg| arg == "--descending" || arg == "--eight")
+    {
+        println!("{}", topology.score_all());
+        println!("{}", topology.rate_all());
+    } else {
+        println!("{}", part1(&input));
+        println!("{}", part2(&input));
+    }
 }
 
 #[cfg(test)]
@@ -133,4 +371,103 @@ mod tests {
         let input = fs::read_to_string("test.txt").expect("test.txt should exist");
         assert_eq!(81, part2(&input));
     }
+
+    #[test]
+    fn test_reach() {
+        let input: String = (0..10)
+            .map(|y| (0..10).map(move |x| char::from_digit((x + y) % 10, 10).unwrap()))
+            .map(|row| row.collect::<String>() + "\n")
+            .collect();
+        let topology = Topology::from(&*input);
+        let reach = topology.reach();
+        assert_eq!(10, reach[0][0].summits.len());
+        assert_eq!(512, reach[0][0].trails);
+    }
+
+    #[test]
+    fn test_trails() {
+        let input = fs::read_to_string("test.txt").expect("test.txt should exist");
+        let topology = Topology::from(&*input);
+        let summary = topology.trailhead_summary();
+        assert_eq!(((2, 0), 5, 20), summary[0]);
+        assert_eq!(((4, 0), 6, 24), summary[1]);
+        let trails: Vec<Vec<(usize, usize)>> = topology.trails(2, 0).collect();
+        assert_eq!(20, trails.len());
+        assert!(trails.iter().all(|trail| trail.len() == 10));
+        assert_eq!(Some(&(2, 0)), trails[0].first());
+
+        let topology = Topology::from("01\n32\n");
+        let expected = "\
+digraph trails {
+    \"0,0\" [label=\"0\"];
+    \"1,0\" [label=\"1\"];
+    \"0,1\" [label=\"3\"];
+    \"1,1\" [label=\"2\"];
+    \"0,0\" -> \"1,0\";
+    \"1,0\" -> \"1,1\";
+    \"1,1\" -> \"0,1\";
+}
+";
+        assert_eq!(expected, topology.to_dot());
+    }
+
+    #[test]
+    fn test_impassable() {
+        let input = "...0...\n...1...\n...2...\n6543456\n7.....7\n8.....8\n9.....9\n";
+        assert_eq!(2, part1(input));
+        let input = "..90..9\n...1.98\n...2..7\n6543456\n765.987\n876....\n987....\n";
+        assert_eq!(4, part1(input));
+        let input = ".....0.\n..4321.\n..5..2.\n..6543.\n..7..4.\n..8765.\n..9....\n";
+        assert_eq!(3, part2(input));
+        let input = "012345\n123456\n234567\n345678\n4.6789\n56789.\n";
+        assert_eq!(227, part2(input));
+    }
+
+    #[test]
+    fn test_rules() {
+        let input = fs::read_to_string("test.txt").expect("test.txt should exist");
+        let topology = Topology::from(&*input).with_rules(Rules::descending());
+        assert_eq!(81, topology.rate_all());
+
+        let topology = Topology::from("0.\n.1\n");
+        assert_eq!(
+            0,
+            topology
+                .with_rules(Rules {
+                    goal: 1,
+                    ..Rules::default()
+                })
+                .rate_all()
+        );
+        let topology = Topology::from("0.\n.1\n").with_rules(Rules {
+            goal: 1,
+            neighbourhood: Neighbourhood::Eight,
+            ..Rules::default()
+        });
+        assert_eq!(1, topology.rate_all());
+
+        let topology = Topology::from("0246\n").with_rules(Rules {
+            step: |current, next| next == current + 2,
+            goal: 6,
+            ..Rules::default()
+        });
+        assert_eq!(1, topology.score_all());
+    }
+
+    #[test]
+    fn test_cyclic_rules() {
+        let topology = Topology::from("0012\n").with_rules(Rules {
+            step: |current, next| next == current || next == current + 1,
+            goal: 2,
+            ..Rules::default()
+        });
+        assert_eq!(None, topology.order());
+        assert_eq!(1, topology.trails(0, 0).count());
+        assert_eq!(1, topology.trails(1, 0).count());
+        assert_eq!(2, topology.rate_all());
+        assert_eq!(2, topology.score_all());
+        let reach = topology.reach();
+        assert_eq!(1, reach[0][0].trails);
+        assert_eq!(0, reach[0][2].trails);
+    }
 }
@@ -1,15 +1,83 @@
+use std::collections::HashMap;
+use std::env;
 use std::fs;
 
+fn parse(input: &str) -> HashMap<usize, usize> {
+    let mut stones = HashMap::new();
+    for stone in input.split_whitespace() {
+        *stones.entry(stone.parse().unwrap()).or_default() += 1;
+    }
+    stones
+}
+
+fn digits(stone: usize) -> u32 {
+    stone.checked_ilog10().unwrap_or(0) + 1
+}
+
+fn change(stone: usize) -> (usize, Option<usize>) {
+    if stone == 0 {
+        return (1, None);
+    }
+    let len = digits(stone);
+    if len.is_multiple_of(2) {
+        let half = 10usize.pow(len / 2);
+        (stone / half, Some(stone % half))
+    } else {
+        (stone * 2024, None)
+    }
+}
+
+fn blink(stones: &HashMap<usize, usize>) -> HashMap<usize, usize> {
+    let mut next = HashMap::with_capacity(stones.len());
+    for (&stone, &count) in stones {
+        let (left, right) = change(stone);
+        *next.entry(left).or_default() += count;
+        if let Some(right) = right {
+            *next.entry(right).or_default() += count;
+        }
+    }
+    next
+}
+
+fn histogram(input: &str, blinks: usize) -> HashMap<usize, usize> {
+    let mut stones = parse(input);
+    for _ in 0..blinks {
+        stones = blink(&stones);
+    }
+    stones
+}
+
+fn count(input: &str, blinks: usize) -> usize {
+    histogram(input, blinks).values().sum()
+}
+
 fn part1(input: &str) -> usize {
-    0
+    count(input, 25)
 }
 
 fn part2(input: &str) -> usize {
-    0
+    count(input, 75)
 }
 
 fn main() {
     let input = fs::read_to_string("input.txt").expect("input.txt should exist");
+    let args: Vec<String> = env::args().collect();
+    if let Some(blinks) = args
+        .iter()
+        .position(|arg| arg == "--blinks")
+        .and_then(|idx| args.get(idx + 1))
+    {
+        let stones = histogram(&input, blinks.parse().expect("blinks should be a number"));
+        if args.iter().any(|arg| arg == "--histogram") {
+            let mut distinct: Vec<(&usize, &usize)> = stones.iter().collect();
+            distinct.sort();
+            for (stone, count) in distinct {
+                println!("{}: {}", stone, count);
+            }
+        }
+        println!("{}", stones.values().sum::<usize>());
+        return;
+    }
     println!("{}", part1(&input));
     println!("{}", part2(&input));
 }
@@ -21,12 +89,27 @@ mod tests {
     #[test]
     fn test_p1() {
         let input = fs::read_to_string("test.txt").expect("test.txt should exist");
-        assert_eq!(0, part1(&input));
+        assert_eq!(55312, part1(&input));
     }
 
     #[test]
     fn test_p2() {
         let input = fs::read_to_string("test.txt").expect("test.txt should exist");
-        assert_eq!(0, part2(&input));
+        assert_eq!(65601038650482, part2(&input));
+    }
+
+    #[test]
+    fn test_histogram() {
+        assert_eq!(
+            HashMap::from([(1, 2), (2024, 1), (0, 1), (9, 2), (2021976, 1)]),
+            histogram("0 1 10 99 999", 1)
+        );
+        let input = fs::read_to_string("test.txt").expect("test.txt should exist");
+        assert_eq!(
+            HashMap::from([(253000, 1), (1, 1), (7, 1)]),
+            histogram(&input, 1)
+        );
+        assert_eq!(22, count(&input, 6));
+        assert_eq!(parse(&input), histogram(&input, 0));
     }
 }
@@ -1,15 +1,257 @@
+use std::collections::{BTreeSet, VecDeque};
+use std::env;
 use std::fs;
 
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Region {
+    plant: char,
+    cells: Vec<(usize, usize)>,
+    perimeter: usize,
+    sides: usize,
+    bounds: ((usize, usize), (usize, usize)),
+    enclosing: Option<usize>,
+}
+
+impl Region {
+    fn area(&self) -> usize {
+        self.cells.len()
+    }
+
+    fn price(&self) -> usize {
+        self.area() * self.perimeter
+    }
+
+    fn bulk_price(&self) -> usize {
+        self.area() * self.sides
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Garden {
+    grid: Vec<Vec<char>>,
+    labels: Vec<Vec<usize>>,
+    regions: Vec<Region>,
+}
+
+impl Garden {
+    fn plant(grid: &[Vec<char>], x: isize, y: isize) -> Option<char> {
+        if x < 0 || y < 0 {
+            return None;
+        }
+        grid.get(y as usize)
+            .and_then(|row| row.get(x as usize))
+            .copied()
+    }
+
+    fn neighbours(x: usize, y: usize) -> [(isize, isize); 4] {
+        let (x, y) = (x as isize, y as isize);
+        [(x - 1, y), (x, y - 1), (x + 1, y), (x, y + 1)]
+    }
+
+    fn flood(
+        grid: &[Vec<char>],
+        labels: &mut [Vec<usize>],
+        id: usize,
+        start: (usize, usize),
+    ) -> Region {
+        let plant = grid[start.1][start.0];
+        let mut cells = Vec::new();
+        let mut queue = VecDeque::from([start]);
+        labels[start.1][start.0] = id;
+        while let Some((x, y)) = queue.pop_front() {
+            cells.push((x, y));
+            for (x2, y2) in Self::neighbours(x, y) {
+                if Self::plant(grid, x2, y2) == Some(plant) {
+                    let (x2, y2) = (x2 as usize, y2 as usize);
+                    if labels[y2][x2] == usize::MAX {
+                        labels[y2][x2] = id;
+                        queue.push_back((x2, y2));
+                    }
+                }
+            }
+        }
+        let same = |x: isize, y: isize| Self::plant(grid, x, y) == Some(plant);
+        let mut perimeter = 0;
+        let mut sides = 0;
+        for &(x, y) in &cells {
+            let (x, y) = (x as isize, y as isize);
+            for (dx, dy) in [(-1, 0), (0, -1), (1, 0), (0, 1)] {
+                if !same(x + dx, y + dy) {
+                    perimeter += 1;
+                }
+            }
+            for (dx, dy) in [(-1, -1), (1, -1), (1, 1), (-1, 1)] {
+                let horizontal = same(x + dx, y);
+                let vertical = same(x, y + dy);
+                let diagonal = same(x + dx, y + dy);
+                if (!horizontal && !vertical) || (horizontal && vertical && !diagonal) {
+                    sides += 1;
+                }
+            }
+        }
+        let min_x = cells.iter().map(|c| c.0).min().unwrap();
+        let min_y = cells.iter().map(|c| c.1).min().unwrap();
+        let max_x = cells.iter().map(|c| c.0).max().unwrap();
+        let max_y = cells.iter().map(|c| c.1).max().unwrap();
+        Region {
+            plant,
+            cells,
+            perimeter,
+            sides,
+            bounds: ((min_x, min_y), (max_x, max_y)),
+            enclosing: None,
+        }
+    }
+
+    fn intersect(idom: &[usize], index: &[usize], mut a: usize, mut b: usize) -> usize {
+        while a != b {
+            while index[a] < index[b] {
+                a = idom[a];
+            }
+            while index[b] < index[a] {
+                b = idom[b];
+            }
+        }
+        a
+    }
+
+    // Regions form a graph with a virtual outside node joined to every region
+    // on the border. A region encloses another exactly when every path from
+    // the outside to it passes through the first, so the innermost encloser
+    // is its immediate dominator, found with the Cooper-Harvey-Kennedy
+    // iteration over a postorder of the graph.
+    fn contain(grid: &[Vec<char>], labels: &[Vec<usize>], regions: &mut [Region]) {
+        let outside = regions.len();
+        let mut adjacent: Vec<BTreeSet<usize>> = vec![BTreeSet::new(); outside + 1];
+        for (y, row) in labels.iter().enumerate() {
+            for (x, &id) in row.iter().enumerate() {
+                for (x2, y2) in Self::neighbours(x, y) {
+                    let other = match Self::plant(grid, x2, y2) {
+                        Some(_) => labels[y2 as usize][x2 as usize],
+                        None => outside,
+                    };
+                    if other != id {
+                        adjacent[id].insert(other);
+                        adjacent[other].insert(id);
+                    }
+                }
+            }
+        }
+        let mut index = vec![usize::MAX; outside + 1];
+        let mut postorder = Vec::new();
+        let mut visited = vec![false; outside + 1];
+        visited[outside] = true;
+        let mut stack = vec![(
+            outside,
+            adjacent[outside].iter().copied().collect::<Vec<_>>(),
+        )];
+        while let Some((node, pending)) = stack.last_mut() {
+            let node = *node;
+            match pending.pop() {
+                Some(next) if !visited[next] => {
+                    visited[next] = true;
+                    stack.push((next, adjacent[next].iter().copied().collect()));
+                }
+                Some(_) => {}
+                None => {
+                    index[node] = postorder.len();
+                    postorder.push(node);
+                    stack.pop();
+                }
+            }
+        }
+        let mut idom = vec![usize::MAX; outside + 1];
+        idom[outside] = outside;
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &node in postorder.iter().rev().skip(1) {
+                let mut dominator = usize::MAX;
+                for &pred in &adjacent[node] {
+                    if idom[pred] == usize::MAX {
+                        continue;
+                    }
+                    dominator = if dominator == usize::MAX {
+                        pred
+                    } else {
+                        Self::intersect(&idom, &index, pred, dominator)
+                    };
+                }
+                if idom[node] != dominator {
+                    idom[node] = dominator;
+                    changed = true;
+                }
+            }
+        }
+        for (id, region) in regions.iter_mut().enumerate() {
+            region.enclosing = (idom[id] != outside).then_some(idom[id]);
+        }
+    }
+
+    fn fence_price(&self) -> usize {
+        self.regions.iter().map(|region| region.price()).sum()
+    }
+
+    fn bulk_fence_price(&self) -> usize {
+        self.regions.iter().map(|region| region.bulk_price()).sum()
+    }
+}
+
+impl From<&str> for Garden {
+    fn from(input: &str) -> Self {
+        let grid: Vec<Vec<char>> = input.lines().map(|line| line.chars().collect()).collect();
+        let mut labels: Vec<Vec<usize>> =
+            grid.iter().map(|row| vec![usize::MAX; row.len()]).collect();
+        let mut regions = Vec::new();
+        for y in 0..grid.len() {
+            for x in 0..grid[y].len() {
+                if labels[y][x] == usize::MAX {
+                    let region = Self::flood(&grid, &mut labels, regions.len(), (x, y));
+                    regions.push(region);
+                }
+            }
+        }
+        Self::contain(&grid, &labels, &mut regions);
+        Garden {
+            grid,
+            labels,
+            regions,
+        }
+    }
+}
+
 fn part1(input: &str) -> usize {
-    0
+    Garden::from(input).fence_price()
 }
 
 fn part2(input: &str) -> usize {
-    0
+    Garden::from(input).bulk_fence_price()
 }
 
 fn main() {
     let input = fs::read_to_string("input.txt").expect("input.txt should exist");
+    if env::args().any(|arg| arg == "--regions") {
+        let garden = Garden::from(&*input);
+        for (id, region) in garden.regions.iter().enumerate() {
+            let ((min_x, min_y), (max_x, max_y)) = region.bounds;
+            print!(
+                "{} {}: area {}, perimeter {}, sides {}, bounds {},{}..{},{}",
+                id,
+                region.plant,
+                region.area(),
+                region.perimeter,
+                region.sides,
+                min_x,
+                min_y,
+                max_x,
+                max_y
+            );
+            match region.enclosing {
+                Some(outer) => println!(", inside {}", outer),
+                None => println!(),
+            }
+        }
+    }
     println!("{}", part1(&input));
     println!("{}", part2(&input));
 }
@@ -21,12 +263,68 @@ mod tests {
     #[test]
     fn test_p1() {
         let input = fs::read_to_string("test.txt").expect("test.txt should exist");
-        assert_eq!(0, part1(&input));
+        assert_eq!(1930, part1(&input));
     }
 
     #[test]
     fn test_p2() {
         let input = fs::read_to_string("test.txt").expect("test.txt should exist");
-        assert_eq!(0, part2(&input));
+        assert_eq!(1206, part2(&input));
+    }
+
+    #[test]
+    fn test_examples() {
+        let input = "AAAA\nBBCD\nBBCC\nEEEC\n";
+        assert_eq!(140, part1(input));
+        assert_eq!(80, part2(input));
+        let input = "OOOOO\nOXOXO\nOOOOO\nOXOXO\nOOOOO\n";
+        assert_eq!(772, part1(input));
+        assert_eq!(436, part2(input));
+        assert_eq!(236, part2("EEEEE\nEXXXX\nEEEEE\nEXXXX\nEEEEE\n"));
+        assert_eq!(
+            368,
+            part2("AAAAAA\nAAABBA\nAAABBA\nABBAAA\nABBAAA\nAAAAAA\n")
+        );
+    }
+
+    #[test]
+    fn test_regions() {
+        let garden = Garden::from("AAAA\nBBCD\nBBCC\nEEEC\n");
+        let c = &garden.regions[garden.labels[1][2]];
+        assert_eq!('C', c.plant);
+        assert_eq!(4, c.area());
+        assert_eq!(10, c.perimeter);
+        assert_eq!(8, c.sides);
+        assert_eq!(((2, 1), (3, 3)), c.bounds);
+        assert_eq!(None, garden.regions[garden.labels[0][0]].enclosing);
+
+        let garden = Garden::from("OOOOO\nOXOXO\nOOOOO\nOXOXO\nOOOOO\n");
+        assert_eq!(5, garden.regions.len());
+        let outer = garden.labels[0][0];
+        assert_eq!(Some(outer), garden.regions[garden.labels[1][1]].enclosing);
+        assert_eq!(Some(outer), garden.regions[garden.labels[3][3]].enclosing);
+        assert_eq!(None, garden.regions[outer].enclosing);
+        assert_eq!(36, garden.regions[outer].perimeter);
+        assert_eq!(20, garden.regions[outer].sides);
+
+        let garden = Garden::from("OOOOO\nOXXXO\nOXAXO\nOXXXO\nOOOOO\n");
+        let (outer, middle, inner) = (
+            garden.labels[0][0],
+            garden.labels[1][1],
+            garden.labels[2][2],
+        );
+        assert_eq!(Some(middle), garden.regions[inner].enclosing);
+        assert_eq!(Some(outer), garden.regions[middle].enclosing);
+        assert_eq!(None, garden.regions[outer].enclosing);
+
+        let garden = Garden::from("OOOOOO\nOXXYYO\nOXAAYO\nOXXYYO\nOOOOOO\n");
+        assert_eq!(
+            Some(garden.labels[0][0]),
+            garden.regions[garden.labels[2][2]].enclosing
+        );
+        assert_eq!(
+            Some(garden.labels[0][0]),
+            garden.regions[garden.labels[1][1]].enclosing
+        );
     }
 }
@@ -1,15 +1,189 @@
+use std::env;
 use std::fs;
 
+const OFFSET: i64 = 10000000000000;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct Solution {
+    a: i64,
+    b: i64,
+}
+
+impl Solution {
+    fn tokens(&self) -> i64 {
+        3 * self.a + self.b
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct Machine {
+    a: (i64, i64),
+    b: (i64, i64),
+    prize: (i64, i64),
+}
+
+impl From<&str> for Machine {
+    fn from(input: &str) -> Self {
+        let mut pairs = input.lines().map(|line| {
+            let mut numbers = line
+                .split(|c: char| !c.is_ascii_digit())
+                .filter(|item| !item.is_empty())
+                .map(|item| item.parse().unwrap());
+            (numbers.next().unwrap(), numbers.next().unwrap())
+        });
+        Machine {
+            a: pairs.next().unwrap(),
+            b: pairs.next().unwrap(),
+            prize: pairs.next().unwrap(),
+        }
+    }
+}
+
+fn ext_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+    if b == 0 {
+        (a, 1, 0)
+    } else {
+        let (g, x, y) = ext_gcd(b, a % b);
+        (g, y, x - (a / b) * y)
+    }
+}
+
+fn div_floor(a: i128, b: i128) -> i128 {
+    a.div_euclid(b)
+}
+
+fn div_ceil(a: i128, b: i128) -> i128 {
+    -(-a).div_euclid(b)
+}
+
+impl Machine {
+    fn det(&self) -> i64 {
+        self.a.0 * self.b.1 - self.a.1 * self.b.0
+    }
+
+    fn is_degenerate(&self) -> bool {
+        self.det() == 0
+    }
+
+    fn solve(&self, offset: i64, limit: Option<i64>) -> Option<Solution> {
+        let prize = (self.prize.0 + offset, self.prize.1 + offset);
+        let solution = if self.is_degenerate() {
+            self.solve_collinear(prize, limit)?
+        } else {
+            let det = self.det();
+            let a = prize.0 * self.b.1 - prize.1 * self.b.0;
+            let b = self.a.0 * prize.1 - self.a.1 * prize.0;
+            if a % det != 0 || b % det != 0 {
+                return None;
+            }
+            Solution {
+                a: a / det,
+                b: b / det,
+            }
+        };
+        let within = |presses: i64| presses >= 0 && limit.is_none_or(|limit| presses <= limit);
+        (within(solution.a) && within(solution.b)).then_some(solution)
+    }
+
+    fn solve_collinear(&self, prize: (i64, i64), limit: Option<i64>) -> Option<Solution> {
+        let on_line = |v: (i64, i64)| v.0 * prize.1 - v.1 * prize.0 == 0;
+        if !on_line(self.a) || !on_line(self.b) {
+            return None;
+        }
+        let (a, b, p) = if self.a.0 != 0 || self.b.0 != 0 {
+            (self.a.0 as i128, self.b.0 as i128, prize.0 as i128)
+        } else {
+            (self.a.1 as i128, self.b.1 as i128, prize.1 as i128)
+        };
+        if a == 0 && b == 0 {
+            return (p == 0).then_some(Solution { a: 0, b: 0 });
+        }
+        let (g, x, y) = ext_gcd(a, b);
+        if p % g != 0 {
+            return None;
+        }
+        let (a0, b0) = (x * (p / g), y * (p / g));
+        let (step_a, step_b) = (b / g, a / g);
+        let mut low = i128::MIN;
+        let mut high = i128::MAX;
+        let mut bound = |coefficient: i128, constant: i128, max: Option<i128>| {
+            if coefficient == 0 {
+                if constant < 0 || max.is_some_and(|max| constant > max) {
+                    low = i128::MAX;
+                }
+            } else if coefficient > 0 {
+                low = low.max(div_ceil(-constant, coefficient));
+                if let Some(max) = max {
+                    high = high.min(div_floor(max - constant, coefficient));
+                }
+            } else {
+                high = high.min(div_floor(constant, -coefficient));
+                if let Some(max) = max {
+                    low = low.max(div_ceil(constant - max, -coefficient));
+                }
+            }
+        };
+        let max = limit.map(|limit| limit as i128);
+        bound(step_a, a0, max);
+        bound(-step_b, b0, max);
+        if low > high {
+            return None;
+        }
+        let slope = 3 * step_a - step_b;
+        let k = if slope >= 0 { low } else { high };
+        if k == i128::MIN || k == i128::MAX {
+            return None;
+        }
+        Some(Solution {
+            a: (a0 + k * step_a) as i64,
+            b: (b0 - k * step_b) as i64,
+        })
+    }
+}
+
+fn parse(input: &str) -> Vec<Machine> {
+    input
+        .split("\n\n")
+        .filter(|block| !block.trim().is_empty())
+        .map(Machine::from)
+        .collect()
+}
+
+fn cost(input: &str, offset: i64, limit: Option<i64>) -> i64 {
+    parse(input)
+        .iter()
+        .filter_map(|machine| machine.solve(offset, limit))
+        .map(|solution| solution.tokens())
+        .sum()
+}
+
 fn part1(input: &str) -> usize {
-    0
+    cost(input, 0, Some(100)) as usize
 }
 
 fn part2(input: &str) -> usize {
-    0
+    cost(input, OFFSET, None) as usize
 }
 
 fn main() {
     let input = fs::read_to_string("input.txt").expect("input.txt should exist");
+    if env::args().any(|arg| arg == "--machines") {
+        for (idx, machine) in parse(&input).iter().enumerate() {
+            for (offset, limit) in [(0, Some(100)), (OFFSET, None)] {
+                match machine.solve(offset, limit) {
+                    Some(solution) => println!(
+                        "{} (+{}): A x{}, B x{}, {} tokens",
+                        idx,
+                        offset,
+                        solution.a,
+                        solution.b,
+                        solution.tokens()
+                    ),
+                    None => println!("{} (+{}): no prize", idx, offset),
+                }
+            }
+        }
+    }
     println!("{}", part1(&input));
     println!("{}", part2(&input));
 }
@@ -21,12 +195,57 @@ mod tests {
     #[test]
     fn test_p1() {
         let input = fs::read_to_string("test.txt").expect("test.txt should exist");
-        assert_eq!(0, part1(&input));
+        assert_eq!(480, part1(&input));
     }
 
     #[test]
     fn test_p2() {
         let input = fs::read_to_string("test.txt").expect("test.txt should exist");
-        assert_eq!(0, part2(&input));
+        assert_eq!(875318608908, part2(&input));
+    }
+
+    #[test]
+    fn test_machines() {
+        let input = fs::read_to_string("test.txt").expect("test.txt should exist");
+        let machines = parse(&input);
+        assert_eq!(4, machines.len());
+        assert_eq!(
+            Some(Solution { a: 80, b: 40 }),
+            machines[0].solve(0, Some(100))
+        );
+        assert_eq!(280, machines[0].solve(0, None).unwrap().tokens());
+        assert_eq!(None, machines[1].solve(0, Some(100)));
+        assert_eq!(
+            Some(Solution { a: 38, b: 86 }),
+            machines[2].solve(0, Some(100))
+        );
+        assert_eq!(None, machines[0].solve(OFFSET, None));
+        assert!(machines[1].solve(OFFSET, None).is_some());
+    }
+
+    #[test]
+    fn test_collinear() {
+        let machine = |a, b, prize| Machine { a, b, prize };
+        let degenerate = machine((1, 1), (3, 3), (10, 10));
+        assert!(degenerate.is_degenerate());
+        assert_eq!(Some(Solution { a: 1, b: 3 }), degenerate.solve(0, None));
+        let degenerate = machine((6, 6), (1, 1), (12, 12));
+        assert_eq!(Some(Solution { a: 2, b: 0 }), degenerate.solve(0, None));
+        assert_eq!(None, machine((2, 2), (4, 4), (7, 7)).solve(0, None));
+        assert_eq!(None, machine((1, 2), (2, 4), (3, 5)).solve(0, None));
+        let degenerate = machine((1, 1), (3, 3), (401, 401));
+        assert_eq!(None, degenerate.solve(0, Some(100)));
+        assert_eq!(
+            Some(Solution { a: 2, b: 133 }),
+            degenerate.solve(0, Some(200))
+        );
+        let degenerate = machine((2, 0), (4, 0), (OFFSET, 0));
+        assert_eq!(
+            Some(Solution {
+                a: 0,
+                b: OFFSET / 4
+            }),
+            degenerate.solve(0, None)
+        );
     }
 }
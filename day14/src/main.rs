@@ -1,32 +1,315 @@
+use std::env;
 use std::fs;
 
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct Robot {
+    p: (i64, i64),
+    v: (i64, i64),
+}
+
+impl From<&str> for Robot {
+    fn from(input: &str) -> Self {
+        let mut numbers = input
+            .split(|c: char| !(c.is_ascii_digit() || c == '-'))
+            .filter(|item| !item.is_empty())
+            .map(|item| item.parse().unwrap());
+        let mut next = || numbers.next().unwrap();
+        Robot {
+            p: (next(), next()),
+            v: (next(), next()),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct Arena {
+    width: i64,
+    height: i64,
+}
+
+impl Arena {
+    const EXAMPLE: Arena = Arena {
+        width: 11,
+        height: 7,
+    };
+    const REAL: Arena = Arena {
+        width: 101,
+        height: 103,
+    };
+
+    fn infer(robots: &[Robot]) -> Arena {
+        let fits = |arena: Arena| {
+            robots
+                .iter()
+                .all(|robot| robot.p.0 < arena.width && robot.p.1 < arena.height)
+        };
+        if fits(Arena::EXAMPLE) {
+            Arena::EXAMPLE
+        } else {
+            Arena::REAL
+        }
+    }
+
+    fn position(&self, robot: &Robot, time: i64) -> (i64, i64) {
+        (
+            (robot.p.0 + robot.v.0 * time).rem_euclid(self.width),
+            (robot.p.1 + robot.v.1 * time).rem_euclid(self.height),
+        )
+    }
+
+    fn positions(&self, robots: &[Robot], time: i64) -> Vec<(i64, i64)> {
+        robots
+            .iter()
+            .map(|robot| self.position(robot, time))
+            .collect()
+    }
+
+    fn safety_factor(&self, positions: &[(i64, i64)]) -> usize {
+        let (mid_x, mid_y) = (self.width / 2, self.height / 2);
+        let mut quadrants = [0; 4];
+        for &(x, y) in positions {
+            if x == mid_x || y == mid_y {
+                continue;
+            }
+            quadrants[(x > mid_x) as usize + 2 * (y > mid_y) as usize] += 1;
+        }
+        quadrants.iter().product()
+    }
+
+    fn grid(&self, positions: &[(i64, i64)]) -> Vec<Vec<bool>> {
+        let mut grid = vec![vec![false; self.width as usize]; self.height as usize];
+        for &(x, y) in positions {
+            grid[y as usize][x as usize] = true;
+        }
+        grid
+    }
+
+    fn render(&self, positions: &[(i64, i64)]) -> String {
+        let mut res = String::new();
+        for row in self.grid(positions) {
+            res.extend(row.iter().map(|&robot| if robot { '#' } else { '.' }));
+            res.push('\n');
+        }
+        res
+    }
+
+    fn pgm(&self, positions: &[(i64, i64)]) -> String {
+        let mut res = format!("P2\n{} {}\n1\n", self.width, self.height);
+        for row in self.grid(positions) {
+            let pixels: Vec<&str> = row
+                .iter()
+                .map(|&robot| if robot { "1" } else { "0" })
+                .collect();
+            res.push_str(&pixels.join(" "));
+            res.push('\n');
+        }
+        res
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Heuristic {
+    SafetyFactor,
+    Entropy,
+    HorizontalRuns,
+}
+
+impl Heuristic {
+    fn entropy(counts: &[usize], total: usize) -> f64 {
+        counts
+            .iter()
+            .filter(|&&count| count > 0)
+            .map(|&count| {
+                let p = count as f64 / total as f64;
+                -p * p.log2()
+            })
+            .sum()
+    }
+
+    fn score(&self, arena: &Arena, positions: &[(i64, i64)]) -> f64 {
+        match self {
+            Heuristic::SafetyFactor => arena.safety_factor(positions) as f64,
+            Heuristic::Entropy => {
+                let mut columns = vec![0; arena.width as usize];
+                let mut rows = vec![0; arena.height as usize];
+                for &(x, y) in positions {
+                    columns[x as usize] += 1;
+                    rows[y as usize] += 1;
+                }
+                Self::entropy(&columns, positions.len()) + Self::entropy(&rows, positions.len())
+            }
+            Heuristic::HorizontalRuns => {
+                let mut longest = 0;
+                for row in arena.grid(positions) {
+                    let mut run = 0;
+                    for robot in row {
+                        run = if robot { run + 1 } else { 0 };
+                        longest = longest.max(run);
+                    }
+                }
+                -(longest as f64)
+            }
+        }
+    }
+}
+
+impl From<&str> for Heuristic {
+    fn from(input: &str) -> Self {
+        match input {
+            "safety" => Heuristic::SafetyFactor,
+            "entropy" => Heuristic::Entropy,
+            "runs" => Heuristic::HorizontalRuns,
+            _ => panic!("unknown heuristic {}", input),
+        }
+    }
+}
+
+fn parse(input: &str) -> Vec<Robot> {
+    input.lines().map(Robot::from).collect()
+}
+
+fn search(robots: &[Robot], arena: &Arena, heuristic: Heuristic) -> i64 {
+    (0..arena.width * arena.height)
+        .map(|time| {
+            let score = heuristic.score(arena, &arena.positions(robots, time));
+            (time, score)
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(time, _)| time)
+        .unwrap()
+}
+
 fn part1(input: &str) -> usize {
-    0
+    let robots = parse(input);
+    let arena = Arena::infer(&robots);
+    arena.safety_factor(&arena.positions(&robots, 100))
 }
 
 fn part2(input: &str) -> usize {
-    0
+    let robots = parse(input);
+    search(&robots, &Arena::infer(&robots), Heuristic::SafetyFactor) as usize
 }
 
 fn main() {
     let input = fs::read_to_string("input.txt").expect("input.txt should exist");
-    println!("{}", part1(&input));
-    println!("{}", part2(&input));
+    let args: Vec<String> = env::args().collect();
+    let value = |flag: &str| {
+        args.iter()
+            .position(|arg| arg == flag)
+            .and_then(|idx| args.get(idx + 1))
+    };
+    if !args
+        .iter()
+        .any(|arg| ["--arena", "--heuristic", "--frame", "--pgm"].contains(&arg.as_str()))
+    {
+        println!("{}", part1(&input));
+        println!("{}", part2(&input));
+        return;
+    }
+    let robots = parse(&input);
+    let arena = match value("--arena").and_then(|arena| arena.split_once('x')) {
+        Some((width, height)) => Arena {
+            width: width.parse().unwrap(),
+            height: height.parse().unwrap(),
+        },
+        None => Arena::infer(&robots),
+    };
+    let heuristic = value("--heuristic").map_or(Heuristic::SafetyFactor, |name| {
+        Heuristic::from(name.as_str())
+    });
+    println!("{}", arena.safety_factor(&arena.positions(&robots, 100)));
+    let time = search(&robots, &arena, heuristic);
+    println!("{}", time);
+    let positions = arena.positions(&robots, time);
+    if args.iter().any(|arg| arg == "--frame") {
+        print!("{}", arena.render(&positions));
+    }
+    if let Some(path) = value("--pgm") {
+        fs::write(path, arena.pgm(&positions)).expect("frame should be writable");
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn tree(time: i64) -> Vec<Robot> {
+        let arena = Arena::REAL;
+        let mut seed: i64 = 42;
+        let mut random = |bound: i64| {
+            seed = (seed * 1103515245 + 12345).rem_euclid(1 << 31);
+            seed % bound
+        };
+        let mut robots = Vec::new();
+        for i in 0..150 {
+            let target = if i < 100 {
+                (60 + i % 10, 60 + i / 10)
+            } else {
+                (random(arena.width), random(arena.height))
+            };
+            let v = (random(201) - 100, random(201) - 100);
+            let p = (
+                (target.0 - v.0 * time).rem_euclid(arena.width),
+                (target.1 - v.1 * time).rem_euclid(arena.height),
+            );
+            robots.push(Robot { p, v });
+        }
+        robots
+    }
+
     #[test]
     fn test_p1() {
         let input = fs::read_to_string("test.txt").expect("test.txt should exist");
-        assert_eq!(0, part1(&input));
+        assert_eq!(12, part1(&input));
     }
 
     #[test]
     fn test_p2() {
+        let input: String = tree(4321)
+            .iter()
+            .map(|robot| {
+                format!(
+                    "p={},{} v={},{}\n",
+                    robot.p.0, robot.p.1, robot.v.0, robot.v.1
+                )
+            })
+            .collect();
+        assert_eq!(4321, part2(&input));
+    }
+
+    #[test]
+    fn test_heuristics() {
+        let robots = tree(777);
+        for heuristic in [Heuristic::Entropy, Heuristic::HorizontalRuns] {
+            assert_eq!(777, search(&robots, &Arena::REAL, heuristic));
+        }
+    }
+
+    #[test]
+    fn test_render() {
         let input = fs::read_to_string("test.txt").expect("test.txt should exist");
-        assert_eq!(0, part2(&input));
+        let robots = parse(&input);
+        let arena = Arena::infer(&robots);
+        assert_eq!(Arena::EXAMPLE, arena);
+        let robot = Robot::from("p=2,4 v=2,-3");
+        assert_eq!((1, 3), arena.position(&robot, 5));
+        let frame = arena.render(&arena.positions(&robots, 100));
+        let expected = "\
+......2..1.
+...........
+1..........
+.11........
+.....1.....
+...12......
+.1....1....
+";
+        let counts: String = expected
+            .chars()
+            .map(|c| if c.is_ascii_digit() { '#' } else { c })
+            .collect();
+        assert_eq!(counts, frame);
+        assert!(arena
+            .pgm(&[(0, 0)])
+            .starts_with("P2\n11 7\n1\n1 0 0 0 0 0 0 0 0 0 0\n"));
     }
 }
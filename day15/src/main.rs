@@ -1,15 +1,170 @@
+use std::env;
+use std::fmt;
 use std::fs;
 
+type Point = (usize, usize);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Warehouse {
+    grid: Vec<Vec<char>>,
+    robot: Point,
+}
+
+impl From<&str> for Warehouse {
+    fn from(input: &str) -> Self {
+        let grid: Vec<Vec<char>> = input.lines().map(|line| line.chars().collect()).collect();
+        let robot = grid
+            .iter()
+            .enumerate()
+            .find_map(|(r, row)| row.iter().position(|&c| c == '@').map(|c| (r, c)))
+            .expect("map should contain a robot");
+        Warehouse { grid, robot }
+    }
+}
+
+impl fmt::Display for Warehouse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in &self.grid {
+            writeln!(f, "{}", row.iter().collect::<String>())?;
+        }
+        Ok(())
+    }
+}
+
+impl Warehouse {
+    fn widen(&self) -> Warehouse {
+        let grid = self
+            .grid
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .flat_map(|&c| match c {
+                        'O' => ['[', ']'],
+                        '@' => ['@', '.'],
+                        _ => [c, c],
+                    })
+                    .collect()
+            })
+            .collect();
+        Warehouse {
+            grid,
+            robot: (self.robot.0, self.robot.1 * 2),
+        }
+    }
+
+    fn direction(instruction: char) -> (isize, isize) {
+        match instruction {
+            '^' => (-1, 0),
+            'v' => (1, 0),
+            '<' => (0, -1),
+            '>' => (0, 1),
+            _ => panic!("unknown move {}", instruction),
+        }
+    }
+
+    fn step(&mut self, instruction: char) -> bool {
+        let (dr, dc) = Self::direction(instruction);
+        let mut pushed = vec![self.robot];
+        let mut idx = 0;
+        while idx < pushed.len() {
+            let (r, c) = pushed[idx];
+            idx += 1;
+            let next = (r.wrapping_add_signed(dr), c.wrapping_add_signed(dc));
+            let mut push = |point: Point| {
+                if !pushed.contains(&point) {
+                    pushed.push(point);
+                }
+            };
+            match self.grid[next.0][next.1] {
+                '#' => return false,
+                'O' => push(next),
+                '[' => {
+                    push(next);
+                    if dr != 0 {
+                        push((next.0, next.1 + 1));
+                    }
+                }
+                ']' => {
+                    push(next);
+                    if dr != 0 {
+                        push((next.0, next.1 - 1));
+                    }
+                }
+                _ => {}
+            }
+        }
+        let contents: Vec<char> = pushed.iter().map(|&(r, c)| self.grid[r][c]).collect();
+        for &(r, c) in &pushed {
+            self.grid[r][c] = '.';
+        }
+        for (&(r, c), content) in pushed.iter().zip(contents) {
+            self.grid[r.wrapping_add_signed(dr)][c.wrapping_add_signed(dc)] = content;
+        }
+        self.robot = (
+            self.robot.0.wrapping_add_signed(dr),
+            self.robot.1.wrapping_add_signed(dc),
+        );
+        true
+    }
+
+    fn gps(&self) -> usize {
+        self.grid
+            .iter()
+            .enumerate()
+            .flat_map(|(r, row)| {
+                row.iter()
+                    .enumerate()
+                    .filter(|(_, &c)| c == 'O' || c == '[')
+                    .map(move |(c, _)| 100 * r + c)
+            })
+            .sum()
+    }
+}
+
+fn parse(input: &str) -> (Warehouse, Vec<char>) {
+    let (map, moves) = input
+        .split_once("\n\n")
+        .expect("input should contain a map and moves");
+    let moves = moves.chars().filter(|c| !c.is_whitespace()).collect();
+    (Warehouse::from(map), moves)
+}
+
+fn simulate(mut warehouse: Warehouse, moves: &[char], replay: bool) -> usize {
+    if replay {
+        println!("Initial state:\n{}", warehouse);
+    }
+    for &instruction in moves {
+        warehouse.step(instruction);
+        if replay {
+            println!("Move {}:\n{}", instruction, warehouse);
+        }
+    }
+    warehouse.gps()
+}
+
 fn part1(input: &str) -> usize {
-    0
+    let (warehouse, moves) = parse(input);
+    simulate(warehouse, &moves, false)
 }
 
 fn part2(input: &str) -> usize {
-    0
+    let (warehouse, moves) = parse(input);
+    simulate(warehouse.widen(), &moves, false)
 }
 
 fn main() {
     let input = fs::read_to_string("input.txt").expect("input.txt should exist");
+    let args: Vec<String> = env::args().collect();
+    if args.iter().any(|arg| arg == "--replay") {
+        let (warehouse, moves) = parse(&input);
+        let warehouse = if args.iter().any(|arg| arg == "--wide") {
+            warehouse.widen()
+        } else {
+            warehouse
+        };
+        println!("{}", simulate(warehouse, &moves, true));
+        return;
+    }
     println!("{}", part1(&input));
     println!("{}", part2(&input));
 }
@@ -21,12 +176,46 @@ mod tests {
     #[test]
     fn test_p1() {
         let input = fs::read_to_string("test.txt").expect("test.txt should exist");
-        assert_eq!(0, part1(&input));
+        assert_eq!(10092, part1(&input));
     }
 
     #[test]
     fn test_p2() {
         let input = fs::read_to_string("test.txt").expect("test.txt should exist");
-        assert_eq!(0, part2(&input));
+        assert_eq!(9021, part2(&input));
+    }
+
+    #[test]
+    fn test_cascade() {
+        let input = fs::read_to_string("test2.txt").expect("test2.txt should exist");
+        let (warehouse, moves) = parse(&input);
+        let mut warehouse = warehouse.widen();
+        for &instruction in &moves {
+            warehouse.step(instruction);
+        }
+        let expected = "\
+##############
+##...[].##..##
+##...@.[]...##
+##....[]....##
+##..........##
+##..........##
+##############
+";
+        assert_eq!(expected, warehouse.to_string());
+        assert_eq!(618, warehouse.gps());
+    }
+
+    #[test]
+    fn test_blocked() {
+        let mut warehouse = Warehouse::from("#####\n#@OO#\n#####");
+        assert!(!warehouse.step('>'));
+        assert_eq!((1, 1), warehouse.robot);
+        let mut wide = Warehouse::from("#####\n#.O.#\n#.O.#\n#.@.#\n#####").widen();
+        assert!(!wide.step('^'));
+        assert_eq!(
+            "##########\n##..[]..##\n##..[]..##\n##..@...##\n##########\n",
+            wide.to_string()
+        );
     }
 }
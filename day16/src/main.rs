@@ -1,11 +1,200 @@
+use pathfinding::prelude::dijkstra_all;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 
+type Point = (usize, usize);
+
+const FORWARD: usize = 1;
+const TURN: usize = 1000;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+enum Facing {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Facing {
+    fn clockwise(&self) -> Facing {
+        match self {
+            Facing::North => Facing::East,
+            Facing::East => Facing::South,
+            Facing::South => Facing::West,
+            Facing::West => Facing::North,
+        }
+    }
+
+    fn counterclockwise(&self) -> Facing {
+        match self {
+            Facing::North => Facing::West,
+            Facing::East => Facing::North,
+            Facing::South => Facing::East,
+            Facing::West => Facing::South,
+        }
+    }
+
+    fn ahead(&self, (r, c): Point) -> Point {
+        match self {
+            Facing::North => (r - 1, c),
+            Facing::East => (r, c + 1),
+            Facing::South => (r + 1, c),
+            Facing::West => (r, c - 1),
+        }
+    }
+
+    fn behind(&self, (r, c): Point) -> Point {
+        match self {
+            Facing::North => (r + 1, c),
+            Facing::East => (r, c - 1),
+            Facing::South => (r - 1, c),
+            Facing::West => (r, c + 1),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+struct State {
+    position: Point,
+    facing: Facing,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Maze {
+    walls: Vec<Vec<bool>>,
+    start: Point,
+    end: Point,
+}
+
+impl From<&str> for Maze {
+    fn from(input: &str) -> Self {
+        let mut start = (0, 0);
+        let mut end = (0, 0);
+        let mut walls = Vec::new();
+        for (r, line) in input.lines().enumerate() {
+            let mut row = Vec::new();
+            for (c, ch) in line.chars().enumerate() {
+                match ch {
+                    'S' => start = (r, c),
+                    'E' => end = (r, c),
+                    _ => {}
+                }
+                row.push(ch == '#');
+            }
+            walls.push(row);
+        }
+        Maze { walls, start, end }
+    }
+}
+
+impl Maze {
+    fn turns(state: &State) -> Vec<(State, usize)> {
+        vec![
+            (
+                State {
+                    facing: state.facing.clockwise(),
+                    ..*state
+                },
+                TURN,
+            ),
+            (
+                State {
+                    facing: state.facing.counterclockwise(),
+                    ..*state
+                },
+                TURN,
+            ),
+        ]
+    }
+
+    fn successors(&self, state: &State) -> Vec<(State, usize)> {
+        let mut res = Self::turns(state);
+        let (r, c) = state.facing.ahead(state.position);
+        if !self.walls[r][c] {
+            res.push((
+                State {
+                    position: (r, c),
+                    ..*state
+                },
+                FORWARD,
+            ));
+        }
+        res
+    }
+
+    fn predecessors(&self, state: &State) -> Vec<(State, usize)> {
+        let mut res = Self::turns(state);
+        let (r, c) = state.facing.behind(state.position);
+        if !self.walls[r][c] {
+            res.push((
+                State {
+                    position: (r, c),
+                    ..*state
+                },
+                FORWARD,
+            ));
+        }
+        res
+    }
+
+    fn costs(
+        start: State,
+        successors: impl FnMut(&State) -> Vec<(State, usize)>,
+    ) -> HashMap<State, usize> {
+        let mut costs: HashMap<State, usize> = dijkstra_all(&start, successors)
+            .into_iter()
+            .map(|(state, (_, cost))| (state, cost))
+            .collect();
+        costs.insert(start, 0);
+        costs
+    }
+
+    fn best_paths(&self) -> Option<(usize, HashSet<Point>)> {
+        let start = State {
+            position: self.start,
+            facing: Facing::East,
+        };
+        let forward = Self::costs(start, |state| self.successors(state));
+        let best = forward
+            .iter()
+            .filter(|(state, _)| state.position == self.end)
+            .map(|(_, &cost)| cost)
+            .min()?;
+        // A state lies on an optimal path when the cheapest way in from the
+        // start plus the cheapest way on to an optimal end adds up to the
+        // best score, so two searches cover every path without listing them.
+        let mut backward: HashMap<State, usize> = HashMap::new();
+        for (&end, _) in forward
+            .iter()
+            .filter(|(state, &cost)| state.position == self.end && cost == best)
+        {
+            for (state, cost) in Self::costs(end, |state| self.predecessors(state)) {
+                let known = backward.entry(state).or_insert(cost);
+                *known = (*known).min(cost);
+            }
+        }
+        let tiles = forward
+            .iter()
+            .filter(|(state, &cost)| backward.get(state).is_some_and(|rest| cost + rest == best))
+            .map(|(state, _)| state.position)
+            .collect();
+        Some((best, tiles))
+    }
+}
+
 fn part1(input: &str) -> usize {
-    0
+    Maze::from(input)
+        .best_paths()
+        .expect("maze should be solvable")
+        .0
 }
 
 fn part2(input: &str) -> usize {
-    0
+    Maze::from(input)
+        .best_paths()
+        .expect("maze should be solvable")
+        .1
+        .len()
 }
 
 fn main() {
@@ -21,12 +210,39 @@ mod tests {
     #[test]
     fn test_p1() {
         let input = fs::read_to_string("test.txt").expect("test.txt should exist");
-        assert_eq!(0, part1(&input));
+        assert_eq!(7036, part1(&input));
     }
 
     #[test]
     fn test_p2() {
         let input = fs::read_to_string("test.txt").expect("test.txt should exist");
-        assert_eq!(0, part2(&input));
+        assert_eq!(45, part2(&input));
+    }
+
+    #[test]
+    fn test_second_example() {
+        let input = fs::read_to_string("test2.txt").expect("test2.txt should exist");
+        assert_eq!(11048, part1(&input));
+        assert_eq!(64, part2(&input));
+    }
+
+    #[test]
+    fn test_many_optimal_paths() {
+        // Every diamond can be rounded above or below at equal cost, so
+        // there are 2^k optimal paths through k diamonds.
+        let k = 40;
+        let wall = "#".repeat(4 * k + 4);
+        let side = format!("##{}##", "...#".repeat(k));
+        let middle = format!("#S{}E#", ".#..".repeat(k));
+        let input = [wall.as_str(), &side, &middle, &side, &wall].join("\n");
+        let (score, tiles) = Maze::from(input.as_str()).best_paths().unwrap();
+        assert_eq!((6 * k + 1) * FORWARD + 4 * k * TURN, score);
+        assert_eq!(9 * k + 2, tiles.len());
+    }
+
+    #[test]
+    fn test_unsolvable() {
+        let maze = Maze::from("#####\n#S#E#\n#####");
+        assert_eq!(None, maze.best_paths());
     }
 }
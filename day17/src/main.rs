@@ -1,15 +1,242 @@
+use std::env;
+use std::fmt;
 use std::fs;
 
-fn part1(input: &str) -> usize {
-    0
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Opcode {
+    Adv,
+    Bxl,
+    Bst,
+    Jnz,
+    Bxc,
+    Out,
+    Bdv,
+    Cdv,
 }
 
-fn part2(input: &str) -> usize {
-    0
+impl From<u8> for Opcode {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => Opcode::Adv,
+            1 => Opcode::Bxl,
+            2 => Opcode::Bst,
+            3 => Opcode::Jnz,
+            4 => Opcode::Bxc,
+            5 => Opcode::Out,
+            6 => Opcode::Bdv,
+            7 => Opcode::Cdv,
+            _ => panic!("invalid opcode {}", value),
+        }
+    }
+}
+
+impl Opcode {
+    fn combo(operand: u8) -> &'static str {
+        ["0", "1", "2", "3", "a", "b", "c", "?"][operand as usize]
+    }
+
+    fn describe(&self, operand: u8) -> String {
+        let combo = Self::combo(operand);
+        match self {
+            Opcode::Adv => format!("adv {}    ; a = a >> {}", combo, combo),
+            Opcode::Bxl => format!("bxl {}    ; b = b ^ {}", operand, operand),
+            Opcode::Bst => format!("bst {}    ; b = {} & 7", combo, combo),
+            Opcode::Jnz => format!("jnz {}    ; if a != 0 goto {}", operand, operand),
+            Opcode::Bxc => format!("bxc {}    ; b = b ^ c", operand),
+            Opcode::Out => format!("out {}    ; output {} & 7", combo, combo),
+            Opcode::Bdv => format!("bdv {}    ; b = a >> {}", combo, combo),
+            Opcode::Cdv => format!("cdv {}    ; c = a >> {}", combo, combo),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Computer {
+    a: u64,
+    b: u64,
+    c: u64,
+    ip: usize,
+    program: Vec<u8>,
+    output: Vec<u8>,
+}
+
+impl From<&str> for Computer {
+    fn from(input: &str) -> Self {
+        let mut registers = [0; 3];
+        let mut program = Vec::new();
+        for line in input.lines() {
+            if let Some((name, value)) = line.split_once(": ") {
+                match name {
+                    "Register A" => registers[0] = value.parse().unwrap(),
+                    "Register B" => registers[1] = value.parse().unwrap(),
+                    "Register C" => registers[2] = value.parse().unwrap(),
+                    "Program" => {
+                        program = value.split(',').map(|item| item.parse().unwrap()).collect()
+                    }
+                    _ => panic!("unknown line {}", line),
+                }
+            }
+        }
+        Computer {
+            a: registers[0],
+            b: registers[1],
+            c: registers[2],
+            ip: 0,
+            program,
+            output: Vec::new(),
+        }
+    }
+}
+
+impl fmt::Display for Computer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "ip={:<3} a={:<16o} b={:<16o} c={:o}",
+            self.ip, self.a, self.b, self.c
+        )
+    }
+}
+
+impl Computer {
+    fn combo(&self, operand: u8) -> u64 {
+        match operand {
+            0..=3 => operand as u64,
+            4 => self.a,
+            5 => self.b,
+            6 => self.c,
+            _ => panic!("reserved combo operand {}", operand),
+        }
+    }
+
+    fn divide(&self, operand: u8) -> u64 {
+        let shift = self.combo(operand).try_into().unwrap_or(u32::MAX);
+        self.a.checked_shr(shift).unwrap_or(0)
+    }
+
+    fn current(&self) -> Option<(Opcode, u8)> {
+        let opcode = *self.program.get(self.ip)?;
+        let operand = *self.program.get(self.ip + 1)?;
+        Some((Opcode::from(opcode), operand))
+    }
+
+    fn step(&mut self) -> bool {
+        let Some((opcode, operand)) = self.current() else {
+            return false;
+        };
+        self.ip += 2;
+        match opcode {
+            Opcode::Adv => self.a = self.divide(operand),
+            Opcode::Bxl => self.b ^= operand as u64,
+            Opcode::Bst => self.b = self.combo(operand) & 7,
+            Opcode::Jnz => {
+                if self.a != 0 {
+                    self.ip = operand as usize;
+                }
+            }
+            Opcode::Bxc => self.b ^= self.c,
+            Opcode::Out => self.output.push((self.combo(operand) & 7) as u8),
+            Opcode::Bdv => self.b = self.divide(operand),
+            Opcode::Cdv => self.c = self.divide(operand),
+        }
+        true
+    }
+
+    fn run(&mut self) -> &[u8] {
+        while self.step() {}
+        &self.output
+    }
+
+    fn trace(&mut self) -> Vec<String> {
+        let mut res = Vec::new();
+        while let Some((opcode, operand)) = self.current() {
+            let before = self.to_string();
+            self.step();
+            res.push(format!("{}  {}", before, opcode.describe(operand)));
+        }
+        res
+    }
+
+    fn disassemble(&self) -> Vec<String> {
+        self.program
+            .chunks(2)
+            .enumerate()
+            .map(|(idx, pair)| match pair {
+                [opcode, operand] => {
+                    format!(
+                        "{:>3}: {}",
+                        idx * 2,
+                        Opcode::from(*opcode).describe(*operand)
+                    )
+                }
+                _ => format!("{:>3}: ???", idx * 2),
+            })
+            .collect()
+    }
+
+    fn with_a(&self, a: u64) -> Computer {
+        Computer {
+            a,
+            ip: 0,
+            output: Vec::new(),
+            ..self.clone()
+        }
+    }
+
+    fn quine(&self) -> Option<u64> {
+        // Each loop consumes the low 3 bits of A, so the last output depends
+        // only on the top octal digit. Match the output from the end,
+        // extending A by one octal digit per level.
+        self.extend_quine(0, self.program.len())
+    }
+
+    fn extend_quine(&self, prefix: u64, remaining: usize) -> Option<u64> {
+        if remaining == 0 {
+            return Some(prefix);
+        }
+        let target = &self.program[remaining - 1..];
+        (0..8).find_map(|digit| {
+            let a = prefix << 3 | digit;
+            if a == 0 || self.with_a(a).run() != target {
+                return None;
+            }
+            self.extend_quine(a, remaining - 1)
+        })
+    }
+}
+
+fn join(output: &[u8]) -> String {
+    output
+        .iter()
+        .map(|value| value.to_string())
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+fn part1(input: &str) -> String {
+    join(Computer::from(input).run())
+}
+
+fn part2(input: &str) -> u64 {
+    Computer::from(input)
+        .quine()
+        .expect("program should have a quine")
 }
 
 fn main() {
     let input = fs::read_to_string("input.txt").expect("input.txt should exist");
+    let args: Vec<String> = env::args().collect();
+    if args.iter().any(|arg| arg == "--disassemble") {
+        for line in Computer::from(input.as_str()).disassemble() {
+            println!("{}", line);
+        }
+    }
+    if args.iter().any(|arg| arg == "--trace") {
+        let mut computer = Computer::from(input.as_str());
+        for line in computer.trace() {
+            println!("{}", line);
+        }
+    }
     println!("{}", part1(&input));
     println!("{}", part2(&input));
 }
@@ -21,12 +248,51 @@ mod tests {
     #[test]
     fn test_p1() {
         let input = fs::read_to_string("test.txt").expect("test.txt should exist");
-        assert_eq!(0, part1(&input));
+        assert_eq!("4,6,3,5,6,3,5,2,1,0", part1(&input));
     }
 
     #[test]
     fn test_p2() {
+        let input = fs::read_to_string("test2.txt").expect("test2.txt should exist");
+        assert_eq!(117440, part2(&input));
+    }
+
+    #[test]
+    fn test_instructions() {
+        let mut computer = Computer::from("Register C: 9\n\nProgram: 2,6");
+        computer.run();
+        assert_eq!(1, computer.b);
+        let mut computer = Computer::from("Register A: 2024\n\nProgram: 0,1,5,4,3,0");
+        assert_eq!("4,2,5,6,7,7,7,7,3,1,0", join(computer.run()));
+        assert_eq!(0, computer.a);
+        let mut computer = Computer::from("Register B: 2024\nRegister C: 43690\n\nProgram: 4,0");
+        computer.run();
+        assert_eq!(44354, computer.b);
+    }
+
+    #[test]
+    fn test_wide_shift() {
+        let mut computer = Computer::from("Register A: 729\n\nProgram: 0,4,5,4");
+        assert_eq!("0", join(computer.run()));
+        let mut computer = Computer::from("Register A: 729\nRegister C: 64\n\nProgram: 6,6,7,6");
+        computer.run();
+        assert_eq!((0, 0), (computer.b, computer.c));
+    }
+
+    #[test]
+    fn test_disassemble() {
         let input = fs::read_to_string("test.txt").expect("test.txt should exist");
-        assert_eq!(0, part2(&input));
+        let computer = Computer::from(input.as_str());
+        assert_eq!(
+            vec![
+                "  0: adv 1    ; a = a >> 1",
+                "  2: out a    ; output a & 7",
+                "  4: jnz 0    ; if a != 0 goto 0",
+            ],
+            computer.disassemble()
+        );
+        let mut traced = computer.clone();
+        assert_eq!(30, traced.trace().len());
+        assert_eq!(join(computer.clone().run()), join(&traced.output));
     }
 }
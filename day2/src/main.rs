@@ -1,56 +1,204 @@
+use std::env;
+use std::fmt;
 use std::fs;
 
-fn all_same_direction(levels: &Vec<isize>) -> bool {
-    levels.windows(2).map(|x| x[0] - x[1]).all(|x| x > 0)
-        || levels.windows(2).map(|x| x[0] - x[1]).all(|x| x < 0)
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+struct SafetyPolicy {
+    min_step: isize,
+    max_step: isize,
+    strict: bool,
+    removals: usize,
 }
 
-fn step_within(levels: &Vec<isize>) -> bool {
-    levels
-        .windows(2)
-        .map(|x| (x[0] - x[1]).abs())
-        .all(|x| (1 <= x) && (x <= 3))
+impl Default for SafetyPolicy {
+    fn default() -> Self {
+        SafetyPolicy {
+            min_step: 1,
+            max_step: 3,
+            strict: true,
+            removals: 0,
+        }
+    }
 }
 
-fn part1(input: &str) -> usize {
-    let mut total = 0;
-    for line in input.lines() {
-        let levels: Vec<isize> = line
-            .split(" ")
-            .map(|x| x.parse::<isize>().unwrap())
-            .collect();
-        if all_same_direction(&levels) && step_within(&levels) {
-            total += 1;
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Reason {
+    DirectionChange(usize),
+    Flat(usize),
+    StepTooSmall(usize),
+    StepTooLarge(usize),
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Reason::DirectionChange(idx) => write!(f, "direction change at index {}", idx),
+            Reason::Flat(idx) => write!(f, "no change at index {}", idx),
+            Reason::StepTooSmall(idx) => write!(f, "step too small at index {}", idx),
+            Reason::StepTooLarge(idx) => write!(f, "step too big at index {}", idx),
         }
     }
-    total
 }
 
-fn part2(input: &str) -> usize {
-    let mut total = 0;
-    for line in input.lines() {
-        let levels: Vec<isize> = line
-            .split(" ")
-            .map(|x| x.parse::<isize>().unwrap())
-            .collect();
-        if all_same_direction(&levels) && step_within(&levels) {
-            total += 1;
-        } else if (0..levels.len())
-            .map(|x| {
-                let mut local = levels.clone();
-                local.remove(x);
-                local
-            })
-            .any(|x| all_same_direction(&x) && step_within(&x))
-        {
-            total += 1;
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Verdict {
+    Safe,
+    Dampened(Vec<usize>),
+    Unsafe(Reason),
+}
+
+impl Verdict {
+    fn is_safe(&self) -> bool {
+        !matches!(self, Verdict::Unsafe(_))
+    }
+}
+
+impl SafetyPolicy {
+    fn with_removals(self, removals: usize) -> Self {
+        SafetyPolicy { removals, ..self }
+    }
+
+    fn valid_step(&self, from: isize, to: isize, sign: isize) -> bool {
+        let step = (to - from) * sign;
+        (step > 0 || (!self.strict && step == 0)) && (self.min_step..=self.max_step).contains(&step)
+    }
+
+    fn first_failure(&self, levels: &[isize]) -> Option<Reason> {
+        let mut direction = 0;
+        for (idx, pair) in levels.windows(2).enumerate() {
+            let idx = idx + 1;
+            let step = pair[1] - pair[0];
+            if step == 0 {
+                if self.strict {
+                    return Some(Reason::Flat(idx));
+                }
+            } else if direction == 0 {
+                direction = step.signum();
+            } else if step.signum() != direction {
+                return Some(Reason::DirectionChange(idx));
+            }
+            if step.abs() > self.max_step {
+                return Some(Reason::StepTooLarge(idx));
+            }
+            if step.abs() < self.min_step {
+                return Some(Reason::StepTooSmall(idx));
+            }
+        }
+        None
+    }
+
+    fn dampen_direction(&self, levels: &[isize], sign: isize) -> Option<Vec<usize>> {
+        let len = levels.len();
+        let mut cost = vec![usize::MAX; len];
+        let mut prev = vec![None; len];
+        for i in 0..len {
+            if i <= self.removals {
+                cost[i] = i;
+            }
+            for j in i.saturating_sub(self.removals + 1)..i {
+                if cost[j] != usize::MAX && self.valid_step(levels[j], levels[i], sign) {
+                    let total = cost[j] + (i - j - 1);
+                    if total < cost[i] {
+                        cost[i] = total;
+                        prev[i] = Some(j);
+                    }
+                }
+            }
+        }
+        let (end, total) = (0..len)
+            .filter(|&i| cost[i] != usize::MAX)
+            .map(|i| (i, cost[i] + (len - 1 - i)))
+            .min_by_key(|&(_, total)| total)?;
+        if total > self.removals {
+            return None;
+        }
+        let mut kept = vec![false; len];
+        let mut current = Some(end);
+        while let Some(i) = current {
+            kept[i] = true;
+            current = prev[i];
+        }
+        Some((0..len).filter(|&i| !kept[i]).collect())
+    }
+
+    fn dampen(&self, levels: &[isize]) -> Option<Vec<usize>> {
+        if levels.is_empty() {
+            return Some(vec![]);
         }
+        [1, -1]
+            .into_iter()
+            .filter_map(|sign| self.dampen_direction(levels, sign))
+            .min_by_key(|removed| removed.len())
     }
-    total
+
+    fn check(&self, levels: &[isize]) -> Verdict {
+        match self.first_failure(levels) {
+            None => Verdict::Safe,
+            Some(reason) => match self.dampen(levels) {
+                Some(removed) => Verdict::Dampened(removed),
+                None => Verdict::Unsafe(reason),
+            },
+        }
+    }
+}
+
+fn parse_levels(line: &str) -> Vec<isize> {
+    line.split(" ")
+        .map(|x| x.parse::<isize>().unwrap())
+        .collect()
+}
+
+fn count_safe(input: &str, policy: SafetyPolicy) -> usize {
+    input
+        .lines()
+        .filter(|line| policy.check(&parse_levels(line)).is_safe())
+        .count()
+}
+
+fn part1(input: &str) -> usize {
+    count_safe(input, SafetyPolicy::default())
+}
+
+fn part2(input: &str) -> usize {
+    count_safe(input, SafetyPolicy::default().with_removals(1))
 }
 
 fn main() {
     let input = fs::read_to_string("input.txt").expect("input.txt should exist");
+    let args: Vec<String> = env::args().collect();
+    let raw = |flag: &str| {
+        args.iter()
+            .position(|arg| arg == flag)
+            .and_then(|idx| args.get(idx + 1))
+    };
+    let value =
+        |flag: &str| raw(flag).map(|value| value.parse().expect("flag value should be a number"));
+    if args.iter().any(|arg| arg == "--explain") {
+        let default = SafetyPolicy::default();
+        let policy = SafetyPolicy {
+            min_step: value("--min").unwrap_or(default.min_step),
+            max_step: value("--max").unwrap_or(default.max_step),
+            strict: !args.iter().any(|arg| arg == "--non-strict"),
+            removals: raw("--removals").map_or(default.removals, |removals| {
+                removals
+                    .parse()
+                    .expect("--removals should be a non-negative count")
+            }),
+        };
+        let mut total = 0;
+        for (idx, line) in input.lines().enumerate() {
+            match policy.check(&parse_levels(line)) {
+                Verdict::Safe => total += 1,
+                Verdict::Dampened(removed) => {
+                    println!("{}: safe after removing {:?}", idx + 1, removed);
+                    total += 1;
+                }
+                Verdict::Unsafe(reason) => println!("{}: unsafe, {}", idx + 1, reason),
+            }
+        }
+        println!("{}", total);
+        return;
+    }
     println!("{}", part1(&input));
     println!("{}", part2(&input));
 }
@@ -70,4 +218,59 @@ mod tests {
         let input = fs::read_to_string("test.txt").expect("test.txt should exist");
         assert_eq!(4, part2(&input));
     }
+
+    #[test]
+    fn test_dampen() {
+        let policy = SafetyPolicy::default().with_removals(1);
+        assert_eq!(Some(vec![]), policy.dampen(&[7, 6, 4, 2, 1]));
+        assert_eq!(Some(1), policy.dampen(&[1, 3, 2, 4, 5]).map(|x| x.len()));
+        assert_eq!(Some(1), policy.dampen(&[8, 6, 4, 4, 1]).map(|x| x.len()));
+        assert_eq!(None, policy.dampen(&[9, 7, 6, 2, 1]));
+        assert_eq!(Some(vec![0]), policy.dampen(&[5, 1, 2, 3]));
+        assert_eq!(Some(vec![3]), policy.dampen(&[1, 2, 3, 9]));
+        let policy = policy.with_removals(2);
+        assert_eq!(Some(vec![3, 4]), policy.dampen(&[9, 7, 6, 2, 1]));
+    }
+
+    #[test]
+    fn test_verdicts() {
+        let policy = SafetyPolicy::default();
+        assert_eq!(Verdict::Safe, policy.check(&[7, 6, 4, 2, 1]));
+        assert_eq!(
+            Verdict::Unsafe(Reason::StepTooLarge(2)),
+            policy.check(&[1, 2, 7, 8, 9])
+        );
+        assert_eq!(
+            Verdict::Unsafe(Reason::DirectionChange(2)),
+            policy.check(&[1, 3, 2, 4, 5])
+        );
+        assert_eq!(
+            Verdict::Unsafe(Reason::Flat(3)),
+            policy.check(&[8, 6, 4, 4, 1])
+        );
+        assert_eq!(
+            Verdict::Dampened(vec![1]),
+            policy.with_removals(1).check(&[1, 5, 2, 3])
+        );
+
+        let policy = SafetyPolicy {
+            min_step: 0,
+            max_step: 5,
+            strict: false,
+            removals: 0,
+        };
+        assert_eq!(Verdict::Safe, policy.check(&[1, 2, 7, 7, 9]));
+        assert_eq!(
+            Verdict::Unsafe(Reason::DirectionChange(3)),
+            policy.check(&[1, 1, 2, 1])
+        );
+        let policy = SafetyPolicy {
+            min_step: 2,
+            ..SafetyPolicy::default()
+        };
+        assert_eq!(
+            Verdict::Unsafe(Reason::StepTooSmall(1)),
+            policy.check(&[1, 2, 4])
+        );
+    }
 }
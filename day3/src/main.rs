@@ -1,63 +1,517 @@
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
 use std::fs;
-use regex::Regex;
+use std::io::{self, Read};
+use std::ops::Range;
 
-struct Mult {
-    l: usize,
-    r: usize
+const ENABLED: &str = "\x1b[32m";
+const DISABLED: &str = "\x1b[31m";
+const DO: &str = "\x1b[1;36m";
+const DONT: &str = "\x1b[1;33m";
+const NEAR_MISS: &str = "\x1b[4;35m";
+const RESET: &str = "\x1b[0m";
+
+const SPECS: [(&str, usize); 6] = [
+    ("mul", 2),
+    ("do", 0),
+    ("don't", 0),
+    ("add", 2),
+    ("undo", 0),
+    ("do_if", 1),
+];
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Instruction {
+    Mul(usize, usize),
+    Do,
+    Dont,
+    Add(usize, usize),
+    Undo,
+    DoIf(usize),
 }
 
-impl Mult {
-    fn execute(&self) -> usize {
-        self.l * self.r
+impl Instruction {
+    fn build(name: &str, args: &[usize]) -> Instruction {
+        match (name, args) {
+            ("mul", &[l, r]) => Instruction::Mul(l, r),
+            ("do", []) => Instruction::Do,
+            ("don't", []) => Instruction::Dont,
+            ("add", &[l, r]) => Instruction::Add(l, r),
+            ("undo", []) => Instruction::Undo,
+            ("do_if", &[limit]) => Instruction::DoIf(limit),
+            _ => panic!("at the disco!"),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Instruction::Mul(..) => "mul",
+            Instruction::Do => "do",
+            Instruction::Dont => "don't",
+            Instruction::Add(..) => "add",
+            Instruction::Undo => "undo",
+            Instruction::DoIf(_) => "do_if",
+        }
     }
 }
 
-impl From<&str> for Mult {
-    fn from(value: &str) -> Self {
-        let re = Regex::new(r"mul\((\d+),(\d+)\)").unwrap();
-        let caps = re.captures(value).unwrap();
-        Mult{
-            l: caps[1].parse().unwrap(),
-            r: caps[2].parse().unwrap()
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Instruction::Mul(l, r) | Instruction::Add(l, r) => {
+                write!(f, "{}({},{})", self.name(), l, r)
+            }
+            Instruction::DoIf(limit) => write!(f, "{}({})", self.name(), limit),
+            _ => write!(f, "{}()", self.name()),
         }
     }
 }
 
-fn part1(input: &str) -> usize {
-    let re = Regex::new(r"mul\(\d+,\d+\)").unwrap();
-    let mults = re.find_iter(input).map(|m| Mult::from(m.as_str())).collect::<Vec<Mult>>();
-    mults.iter().map(|m| m.execute()).sum()
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+struct Token {
+    offset: usize,
+    len: usize,
+    instruction: Instruction,
 }
 
-fn part2(input: &str) -> usize {
-    let re = Regex::new(r"(?<all>(?<op>mul|do|don't)\((\d+,\d+)*\))").unwrap();
-    let mut mults = Vec::new();
-    let mut running = true;
-    for token in re.captures_iter(input) {
-        match token.name("op").unwrap().as_str() {
-            "mul" => {
-                if running {
-                    mults.push(Mult::from(token.name("all").unwrap().as_str()))
+#[derive(Clone, Debug)]
+enum Candidate {
+    Name {
+        start: usize,
+        matched: Vec<u8>,
+    },
+    Operand {
+        start: usize,
+        spec: usize,
+        args: Vec<usize>,
+        value: usize,
+        digits: usize,
+    },
+}
+
+#[derive(Clone, Debug)]
+enum Advance {
+    Continue(Candidate),
+    Done(Token),
+    Dead,
+}
+
+#[derive(Clone, Debug, Default)]
+struct Scanner {
+    position: usize,
+    active: Vec<Candidate>,
+    near_misses: Option<Vec<(&'static str, Range<usize>)>>,
+}
+
+impl Scanner {
+    fn recording() -> Self {
+        Scanner {
+            near_misses: Some(Vec::new()),
+            ..Scanner::default()
+        }
+    }
+
+    fn feed(&mut self, bytes: &[u8]) -> Vec<Token> {
+        let mut tokens = Vec::new();
+        for &byte in bytes {
+            tokens.extend(self.step(byte));
+            self.position += 1;
+        }
+        tokens
+    }
+
+    fn token(&self, start: usize, spec: usize, args: &[usize]) -> Token {
+        Token {
+            offset: start,
+            len: self.position + 1 - start,
+            instruction: Instruction::build(SPECS[spec].0, args),
+        }
+    }
+
+    fn near_miss(&self, candidate: &Candidate) -> Option<(&'static str, Range<usize>)> {
+        match candidate {
+            Candidate::Name { start, matched } => SPECS
+                .iter()
+                .map(|(name, _)| *name)
+                .filter(|name| matched.starts_with(name.as_bytes()))
+                .max_by_key(|name| name.len())
+                .map(|name| (name, *start..*start + name.len())),
+            Candidate::Operand { start, spec, .. } => Some((SPECS[*spec].0, *start..self.position)),
+        }
+    }
+
+    fn advance(&self, candidate: Candidate, byte: u8) -> Advance {
+        match candidate {
+            Candidate::Name { start, mut matched } => {
+                let call = SPECS
+                    .iter()
+                    .position(|(name, _)| byte == b'(' && matched == name.as_bytes());
+                matched.push(byte);
+                if let Some(spec) = call {
+                    Advance::Continue(Candidate::Operand {
+                        start,
+                        spec,
+                        args: Vec::new(),
+                        value: 0,
+                        digits: 0,
+                    })
+                } else if SPECS
+                    .iter()
+                    .any(|(name, _)| name.as_bytes().starts_with(&matched))
+                {
+                    Advance::Continue(Candidate::Name { start, matched })
+                } else {
+                    Advance::Dead
                 }
             }
-            "do" => {
-                if token.name("all").unwrap().as_str() == "do()" {
-                    running = true
+            Candidate::Operand {
+                start,
+                spec,
+                mut args,
+                value,
+                digits,
+            } => {
+                let arity = SPECS[spec].1;
+                match byte {
+                    b'0'..=b'9' if digits < 3 && args.len() < arity => {
+                        Advance::Continue(Candidate::Operand {
+                            start,
+                            spec,
+                            args,
+                            value: value * 10 + (byte - b'0') as usize,
+                            digits: digits + 1,
+                        })
+                    }
+                    b',' if digits > 0 && args.len() + 1 < arity => {
+                        args.push(value);
+                        Advance::Continue(Candidate::Operand {
+                            start,
+                            spec,
+                            args,
+                            value: 0,
+                            digits: 0,
+                        })
+                    }
+                    b')' if arity == 0 && digits == 0 => {
+                        Advance::Done(self.token(start, spec, &args))
+                    }
+                    b')' if digits > 0 && args.len() + 1 == arity => {
+                        args.push(value);
+                        Advance::Done(self.token(start, spec, &args))
+                    }
+                    _ => Advance::Dead,
                 }
             }
-            "don't" => {
-                if token.name("all").unwrap().as_str() == "don't()" {
-                    running = false
+        }
+    }
+
+    fn step(&mut self, byte: u8) -> Vec<Token> {
+        let mut tokens = Vec::new();
+        let mut active = Vec::new();
+        for candidate in std::mem::take(&mut self.active) {
+            let miss = self
+                .near_misses
+                .is_some()
+                .then(|| self.near_miss(&candidate))
+                .flatten();
+            match self.advance(candidate, byte) {
+                Advance::Continue(next) => active.push(next),
+                Advance::Done(token) => tokens.push(token),
+                Advance::Dead => {
+                    if let Some(near_misses) = &mut self.near_misses {
+                        near_misses.extend(miss);
+                    }
                 }
             }
-            _ => panic!("at the disco!")
         }
+        if SPECS.iter().any(|(name, _)| name.as_bytes()[0] == byte) {
+            active.push(Candidate::Name {
+                start: self.position,
+                matched: vec![byte],
+            });
+        }
+        self.active = active;
+        tokens
+    }
+}
+
+fn scan(mut reader: impl Read) -> io::Result<Vec<Token>> {
+    let mut scanner = Scanner::default();
+    let mut tokens = Vec::new();
+    let mut buffer = [0; 4096];
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            return Ok(tokens);
+        }
+        tokens.extend(scanner.feed(&buffer[..read]));
+    }
+}
+
+fn tokenize(input: &str) -> Vec<Token> {
+    scan(input.as_bytes()).expect("reading from memory should not fail")
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Outcome {
+    Applied,
+    Skipped,
+    Unhandled,
+    Shadowed,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+struct Event {
+    token: Token,
+    outcome: Outcome,
+}
+
+#[derive(Clone, Debug)]
+struct Machine {
+    total: usize,
+    enabled: bool,
+    results: Vec<usize>,
+    trace: Vec<Event>,
+}
+
+impl Machine {
+    fn new() -> Self {
+        Machine {
+            total: 0,
+            enabled: true,
+            results: Vec::new(),
+            trace: Vec::new(),
+        }
+    }
+
+    fn record(&mut self, value: usize) -> Outcome {
+        if !self.enabled {
+            return Outcome::Skipped;
+        }
+        self.total += value;
+        self.results.push(value);
+        Outcome::Applied
+    }
+
+    fn skipped(&self) -> impl Iterator<Item = &Event> {
+        self.trace
+            .iter()
+            .filter(|event| event.outcome == Outcome::Skipped)
+    }
+}
+
+type Handler = fn(&mut Machine, Instruction) -> Outcome;
+
+fn mul(machine: &mut Machine, instruction: Instruction) -> Outcome {
+    match instruction {
+        Instruction::Mul(l, r) => machine.record(l * r),
+        _ => Outcome::Unhandled,
+    }
+}
+
+fn enable(machine: &mut Machine, _: Instruction) -> Outcome {
+    machine.enabled = true;
+    Outcome::Applied
+}
+
+fn disable(machine: &mut Machine, _: Instruction) -> Outcome {
+    machine.enabled = false;
+    Outcome::Applied
+}
+
+fn add(machine: &mut Machine, instruction: Instruction) -> Outcome {
+    match instruction {
+        Instruction::Add(l, r) => machine.record(l + r),
+        _ => Outcome::Unhandled,
+    }
+}
+
+fn undo(machine: &mut Machine, _: Instruction) -> Outcome {
+    if !machine.enabled {
+        return Outcome::Skipped;
+    }
+    match machine.results.pop() {
+        Some(value) => {
+            machine.total -= value;
+            Outcome::Applied
+        }
+        None => Outcome::Skipped,
+    }
+}
+
+fn enable_below(machine: &mut Machine, instruction: Instruction) -> Outcome {
+    match instruction {
+        Instruction::DoIf(limit) => {
+            machine.enabled = machine.total < limit;
+            Outcome::Applied
+        }
+        _ => Outcome::Unhandled,
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+struct Interpreter {
+    handlers: HashMap<&'static str, Handler>,
+}
+
+impl Interpreter {
+    fn with(mut self, name: &'static str, handler: Handler) -> Self {
+        self.handlers.insert(name, handler);
+        self
+    }
+
+    fn plain() -> Self {
+        Interpreter::default().with("mul", mul)
+    }
+
+    fn conditional() -> Self {
+        Interpreter::plain()
+            .with("do", enable)
+            .with("don't", disable)
+    }
+
+    fn extended() -> Self {
+        Interpreter::conditional()
+            .with("add", add)
+            .with("undo", undo)
+            .with("do_if", enable_below)
+    }
+
+    fn handles(&self, name: &str) -> bool {
+        self.handlers.contains_key(name)
+    }
+
+    // Tokens can only overlap by sharing their closing `)`, so overlapping
+    // tokens sit next to each other in the stream. The longest one this
+    // interpreter handles wins and shadows the rest. Unhandled tokens never
+    // shadow, which keeps the `do()` inside `undo()` live for part 2.
+    fn shadowed(&self, tokens: &[Token], idx: usize) -> bool {
+        let end = |token: &Token| token.offset + token.len;
+        let token = &tokens[idx];
+        let before = tokens[..idx].iter().rev();
+        let after = tokens[idx + 1..].iter();
+        before
+            .take_while(|other| end(other) == end(token))
+            .chain(after.take_while(|other| end(other) == end(token)))
+            .any(|other| other.len > token.len && self.handles(other.instruction.name()))
+    }
+
+    fn run(&self, tokens: &[Token]) -> Machine {
+        let mut machine = Machine::new();
+        for (idx, &token) in tokens.iter().enumerate() {
+            let outcome = match self.handlers.get(token.instruction.name()) {
+                _ if self.shadowed(tokens, idx) => Outcome::Shadowed,
+                Some(handler) => handler(&mut machine, token.instruction),
+                None => Outcome::Unhandled,
+            };
+            machine.trace.push(Event { token, outcome });
+        }
+        machine
+    }
+}
+
+// The scanner gives up on a near miss at the first byte the strict grammar
+// rejects. If the rest still reads as a spaced-out call such as
+// `mul ( 2 , 4 )`, stretch the miss to its closing `)`.
+fn loose_end(bytes: &[u8], end: usize) -> usize {
+    for (idx, &byte) in bytes.iter().enumerate().skip(end) {
+        match byte {
+            b')' => return idx + 1,
+            b'(' | b',' | b'0'..=b'9' => {}
+            byte if byte.is_ascii_whitespace() => {}
+            _ => break,
+        }
+    }
+    end
+}
+
+fn annotate(input: &str, interpreter: &Interpreter) -> String {
+    let mut scanner = Scanner::recording();
+    let tokens = scanner.feed(input.as_bytes());
+    let mut styles: Vec<Option<&str>> = vec![None; input.len()];
+    let bytes = input.as_bytes();
+    for (name, miss) in scanner.near_misses.unwrap_or_default() {
+        if !interpreter.handles(name) {
+            continue;
+        }
+        // A bare name only counts when a spaced-out `(` follows it, so words
+        // like `do` in the surrounding noise stay plain.
+        let bare = miss.len() == name.len();
+        let next = bytes[miss.end..]
+            .iter()
+            .find(|byte| !byte.is_ascii_whitespace());
+        if bare && next != Some(&b'(') {
+            continue;
+        }
+        let end = loose_end(bytes, miss.end);
+        styles[miss.start..end].fill(Some(NEAR_MISS));
+    }
+    for event in interpreter.run(&tokens).trace {
+        let style = match (event.token.instruction, event.outcome) {
+            (Instruction::Mul(..), Outcome::Applied) => ENABLED,
+            (Instruction::Mul(..), Outcome::Skipped) => DISABLED,
+            (_, Outcome::Shadowed) => continue,
+            (Instruction::Do, _) => DO,
+            (Instruction::Dont, _) => DONT,
+            (_, Outcome::Applied) => ENABLED,
+            (_, Outcome::Skipped) => DISABLED,
+            (_, Outcome::Unhandled) => continue,
+        };
+        let span = event.token.offset..event.token.offset + event.token.len;
+        styles[span].fill(Some(style));
+    }
+    let mut res = String::new();
+    let mut current = None;
+    for (idx, c) in input.char_indices() {
+        if styles[idx] != current {
+            if current.is_some() {
+                res.push_str(RESET);
+            }
+            if let Some(style) = styles[idx] {
+                res.push_str(style);
+            }
+            current = styles[idx];
+        }
+        res.push(c);
+    }
+    if current.is_some() {
+        res.push_str(RESET);
     }
-    mults.iter().map(|m| m.execute()).sum()
+    res
+}
+
+fn part1(input: &str) -> usize {
+    Interpreter::plain().run(&tokenize(input)).total
+}
+
+fn part2(input: &str) -> usize {
+    Interpreter::conditional().run(&tokenize(input)).total
 }
 
 fn main() {
     let input = fs::read_to_string("input.txt").expect("input.txt should exist");
+    let args: Vec<String> = env::args().collect();
+    let interpreter = if args.iter().any(|arg| arg == "--extended") {
+        Interpreter::extended()
+    } else {
+        Interpreter::conditional()
+    };
+    if args.iter().any(|arg| arg == "--annotate") {
+        println!("{}", annotate(&input, &interpreter));
+        return;
+    }
+    if args.iter().any(|arg| arg == "--trace") {
+        let machine = interpreter.run(&tokenize(&input));
+        for event in machine.skipped() {
+            println!(
+                "skipped {} at byte {}",
+                event.token.instruction, event.token.offset
+            );
+        }
+        println!("{}", machine.total);
+        return;
+    }
     println!("{}", part1(&input));
     println!("{}", part2(&input));
 }
@@ -77,4 +531,165 @@ mod tests {
         let input = fs::read_to_string("test.txt").expect("test.txt should exist");
         assert_eq!(48, part2(&input));
     }
+
+    #[test]
+    fn test_tokenize() {
+        let input = fs::read_to_string("test.txt").expect("test.txt should exist");
+        let tokens = tokenize(&input);
+        assert_eq!(
+            Token {
+                offset: 1,
+                len: 8,
+                instruction: Instruction::Mul(2, 4)
+            },
+            tokens[0]
+        );
+        assert_eq!(
+            Token {
+                offset: 20,
+                len: 7,
+                instruction: Instruction::Dont
+            },
+            tokens[1]
+        );
+        assert_eq!(7, tokens.len());
+
+        let mut scanner = Scanner::default();
+        let streamed: Vec<Token> = input
+            .as_bytes()
+            .chunks(3)
+            .flat_map(|chunk| scanner.feed(chunk))
+            .collect();
+        assert_eq!(tokens, streamed);
+
+        let instructions: Vec<Instruction> =
+            tokenize("mul(1234,5)mul(123,4)mmul(1,2)do(1)dodo()mul(,1)")
+                .into_iter()
+                .map(|token| token.instruction)
+                .collect();
+        assert_eq!(
+            vec![
+                Instruction::Mul(123, 4),
+                Instruction::Mul(1, 2),
+                Instruction::Do
+            ],
+            instructions
+        );
+    }
+
+    fn run(interpreter: Interpreter, input: &str) -> Machine {
+        interpreter.run(&tokenize(input))
+    }
+
+    #[test]
+    fn test_mul() {
+        assert_eq!(8, run(Interpreter::plain(), "mul(2,4)don't()").total);
+        let machine = run(Interpreter::conditional(), "mul(2,4)don't()mul(3,3)");
+        assert_eq!(8, machine.total);
+        assert_eq!(
+            vec!["mul(3,3)".to_string()],
+            machine
+                .skipped()
+                .map(|event| event.token.instruction.to_string())
+                .collect::<Vec<String>>()
+        );
+    }
+
+    #[test]
+    fn test_do() {
+        let machine = run(Interpreter::conditional(), "don't()mul(2,4)do()mul(3,3)");
+        assert_eq!(9, machine.total);
+        assert!(machine.enabled);
+        let machine = run(Interpreter::plain(), "don't()mul(2,4)");
+        assert_eq!(8, machine.total);
+        assert_eq!(Outcome::Unhandled, machine.trace[0].outcome);
+    }
+
+    #[test]
+    fn test_dont() {
+        let input = fs::read_to_string("test.txt").expect("test.txt should exist");
+        let machine = run(Interpreter::conditional(), &input);
+        let skipped: Vec<usize> = machine.skipped().map(|event| event.token.offset).collect();
+        assert_eq!(vec![28, 48], skipped);
+        assert!(!run(Interpreter::conditional(), "don't()").enabled);
+    }
+
+    #[test]
+    fn test_add() {
+        assert_eq!(7, run(Interpreter::extended(), "add(3,4)").total);
+        assert_eq!(0, run(Interpreter::conditional(), "add(3,4)").total);
+        assert_eq!(0, run(Interpreter::extended(), "don't()add(3,4)").total);
+    }
+
+    #[test]
+    fn test_undo() {
+        let input = fs::read_to_string("test.txt").expect("test.txt should exist");
+        assert_eq!(8, run(Interpreter::extended(), &input).total);
+        let machine = run(
+            Interpreter::extended(),
+            "undo()mul(2,4)add(1,1)undo()undo()",
+        );
+        assert_eq!(0, machine.total);
+        assert_eq!(Outcome::Skipped, machine.trace[0].outcome);
+    }
+
+    #[test]
+    fn test_overlap() {
+        let input = "don't()undo()mul(3,3)";
+        let machine = run(Interpreter::extended(), input);
+        assert_eq!(0, machine.total);
+        assert_eq!(Outcome::Skipped, machine.trace[1].outcome);
+        assert_eq!(Outcome::Shadowed, machine.trace[2].outcome);
+        let machine = run(Interpreter::conditional(), input);
+        assert_eq!(9, machine.total);
+        assert_eq!(Outcome::Unhandled, machine.trace[1].outcome);
+        assert_eq!(Outcome::Applied, machine.trace[2].outcome);
+    }
+
+    #[test]
+    fn test_do_if() {
+        let machine = run(
+            Interpreter::extended(),
+            "mul(5,5)do_if(20)mul(2,2)do_if(100)mul(3,3)",
+        );
+        assert_eq!(34, machine.total);
+        assert_eq!(Outcome::Skipped, machine.trace[2].outcome);
+    }
+
+    #[test]
+    fn test_annotate() {
+        let input = "xmul(2,4)don't()mul(5,5)mul(4*mul ( 2 , 4 )do()";
+        let expected = format!(
+            "x{ENABLED}mul(2,4){RESET}{DONT}don't(){RESET}{DISABLED}mul(5,5){RESET}\
+             {NEAR_MISS}mul(4{RESET}*{NEAR_MISS}mul ( 2 , 4 ){RESET}{DO}do(){RESET}"
+        );
+        assert_eq!(expected, annotate(input, &Interpreter::conditional()));
+
+        let input = fs::read_to_string("test.txt").expect("test.txt should exist");
+        let annotated = annotate(&input, &Interpreter::conditional());
+        assert!(annotated.contains(&format!("un{DO}do(){RESET}")));
+        assert!(annotated.contains(&format!("{NEAR_MISS}mul(32,64{RESET}]")));
+
+        let mut scanner = Scanner::default();
+        scanner.feed(input.as_bytes());
+        assert_eq!(None, scanner.near_misses);
+        let mut scanner = Scanner::recording();
+        scanner.feed(b"mul ( 2 , 4 )mul (x");
+        assert_eq!(
+            Some(vec![("mul", 0..3), ("mul", 13..16)]),
+            scanner.near_misses
+        );
+
+        let input = "add (1,2)what do you do_ifmul(2,3)";
+        assert_eq!(
+            format!("add (1,2)what do you do_if{ENABLED}mul(2,3){RESET}"),
+            annotate(input, &Interpreter::conditional())
+        );
+        assert_eq!(
+            format!("{NEAR_MISS}add (1,2){RESET}what do you do_if{ENABLED}mul(2,3){RESET}"),
+            annotate(input, &Interpreter::extended())
+        );
+        assert_eq!(13, loose_end(b"mul ( 2 , 4 )mul (x", 3));
+        assert_eq!(16, loose_end(b"mul ( 2 , 4 )mul (x", 16));
+    }
 }
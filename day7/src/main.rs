@@ -1,14 +1,57 @@
 use rayon::prelude::*;
+use std::env;
+use std::fmt::Debug;
 use std::fs;
+use std::iter::Sum;
+use std::str::FromStr;
+
+trait Operand: Copy + Ord + Debug + FromStr + Sum + Send + Sync {
+    fn checked_add(self, other: Self) -> Option<Self>;
+    fn checked_mul(self, other: Self) -> Option<Self>;
+    fn checked_shift(self, digits: u32) -> Option<Self>;
+    fn digits(self) -> u32;
+}
+
+macro_rules! operand {
+    ($t:ty) => {
+        impl Operand for $t {
+            fn checked_add(self, other: Self) -> Option<Self> {
+                <$t>::checked_add(self, other)
+            }
+
+            fn checked_mul(self, other: Self) -> Option<Self> {
+                <$t>::checked_mul(self, other)
+            }
+
+            fn checked_shift(self, digits: u32) -> Option<Self> {
+                (10 as $t)
+                    .checked_pow(digits)
+                    .and_then(|scale| self.checked_mul(scale))
+            }
+
+            fn digits(self) -> u32 {
+                self.checked_ilog10().unwrap_or(0) + 1
+            }
+        }
+    };
+}
+
+operand!(usize);
+operand!(u128);
+
+type Operator<T> = fn(T, T) -> Option<T>;
 
 #[derive(Clone, Debug)]
-struct Equation {
-    left: usize,
-    right: Vec<usize>,
+struct Equation<T> {
+    left: T,
+    right: Vec<T>,
 }
 
-impl From<&str> for Equation {
-    fn from(input: &str) -> Equation {
+impl<T: Operand> From<&str> for Equation<T>
+where
+    T::Err: Debug,
+{
+    fn from(input: &str) -> Equation<T> {
         let mut splits = input.split(": ");
         let left = splits.next().unwrap().parse().unwrap();
         let splits = splits.next().unwrap().split(" ");
@@ -17,23 +60,17 @@ impl From<&str> for Equation {
     }
 }
 
-impl Equation {
-    fn test_all(
-        &self,
-        max: usize,
-        current: usize,
-        items: &Vec<usize>,
-        operators: &[fn(usize, usize) -> usize],
-    ) -> Vec<usize> {
+impl<T: Operand> Equation<T> {
+    fn test_all(&self, max: T, current: T, items: &[T], operators: &[Operator<T>]) -> Vec<T> {
         if current > max {
             return vec![];
         }
-        if items.len() > 0 {
+        if let Some((&item, rest)) = items.split_first() {
             let mut results = Vec::new();
-            let mut local = items.clone();
-            let item = local.remove(0);
             for op in operators {
-                results.extend(self.test_all(max, op(current, item), &local, operators));
+                if let Some(next) = op(current, item) {
+                    results.extend(self.test_all(max, next, rest, operators));
+                }
             }
             results
         } else {
@@ -41,48 +78,54 @@ impl Equation {
         }
     }
 
-    fn valid(&self, operators: &[fn(usize, usize) -> usize]) -> bool {
-        let mut local = self.right.clone();
-        let start = local.remove(0);
-        self.test_all(self.left, start, &local, operators)
+    fn valid(&self, operators: &[Operator<T>]) -> bool {
+        let (&start, rest) = self.right.split_first().unwrap();
+        self.test_all(self.left, start, rest, operators)
             .contains(&self.left)
     }
 }
 
-fn add(a: usize, b: usize) -> usize {
-    a + b
+fn add<T: Operand>(a: T, b: T) -> Option<T> {
+    a.checked_add(b)
 }
 
-fn mult(a: usize, b: usize) -> usize {
-    a * b
+fn mult<T: Operand>(a: T, b: T) -> Option<T> {
+    a.checked_mul(b)
 }
 
-fn part1(input: &str) -> usize {
+fn concat<T: Operand>(a: T, b: T) -> Option<T> {
+    a.checked_shift(b.digits())?.checked_add(b)
+}
+
+fn calibrate<T: Operand>(input: &str, operators: &[Operator<T>]) -> T
+where
+    T::Err: Debug,
+{
     input
         .par_lines()
-        .map(|line| Equation::from(line))
-        .filter(|e| e.valid(&[add, mult]))
+        .map(Equation::<T>::from)
+        .filter(|e| e.valid(operators))
         .map(|r| r.left)
         .sum()
 }
 
-fn concat(a: usize, b: usize) -> usize {
-    (a.to_string() + &*b.to_string()).parse().unwrap()
+fn part1(input: &str) -> usize {
+    calibrate(input, &[add, mult])
 }
 
 fn part2(input: &str) -> usize {
-    input
-        .par_lines()
-        .map(|line| Equation::from(line))
-        .filter(|e| e.valid(&[add, mult, concat]))
-        .map(|r| r.left)
-        .sum()
+    calibrate(input, &[add, mult, concat])
 }
 
 fn main() {
     let input = fs::read_to_string("input.txt").expect("input.txt should exist");
-    println!("{}", part1(&input));
-    println!("{}", part2(&input));
+    if env::args().any(|arg| arg == "--wide") {
+        println!("{}", calibrate::<u128>(&input, &[add, mult]));
+        println!("{}", calibrate::<u128>(&input, &[add, mult, concat]));
+    } else {
+        println!("{}", part1(&input));
+        println!("{}", part2(&input));
+    }
 }
 
 #[cfg(test)]
@@ -100,4 +143,23 @@ mod tests {
         let input = fs::read_to_string("test.txt").expect("test.txt should exist");
         assert_eq!(11387, part2(&input));
     }
+
+    #[test]
+    fn test_concat() {
+        assert_eq!(Some(12345), concat(12usize, 345));
+        assert_eq!(Some(100), concat(10usize, 0));
+        assert_eq!(None, concat(usize::MAX / 10, 10));
+        assert_eq!(None, mult(usize::MAX, 2));
+    }
+
+    #[test]
+    fn test_wide() {
+        let input = fs::read_to_string("test.txt").expect("test.txt should exist");
+        assert_eq!(11387u128, calibrate(&input, &[add, mult, concat]));
+        let big = "184467440737095516150: 18446744073709551615 10";
+        assert_eq!(
+            184467440737095516150u128,
+            calibrate(big, &[add, mult, concat])
+        );
+    }
 }
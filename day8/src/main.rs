@@ -1,231 +1,176 @@
-use std::cmp::{max, min};
 use itertools::Itertools;
 use rayon::prelude::*;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::env;
 use std::fs;
 
+type Point = (usize, usize);
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 struct Map {
     hwidth: usize,
     vwidth: usize,
     grid: Vec<Vec<char>>,
-    antenna: HashSet<char>,
-    antenna_locs: Vec<(usize, usize)>,
+    antennas: HashMap<char, Vec<Point>>,
 }
 
 impl Map {
-    fn dist(a: (usize, usize), b: (usize, usize)) -> usize {
-        ((a.0 as isize - b.0 as isize).abs() + (a.1 as isize - b.1 as isize).abs()) as usize
+    fn gcd(a: isize, b: isize) -> isize {
+        if b == 0 {
+            a.abs()
+        } else {
+            Self::gcd(b, a % b)
+        }
     }
 
-    fn points(&self, a: (usize, usize), b: (usize, usize)) -> Vec<(isize, isize)> {
-        let x0 = a.0 as isize;
-        let x1 = b.0 as isize;
-        let y0 = a.1 as isize;
-        let y1 = b.1 as isize;
-        let x_dist = (x0 - x1).abs();
-        let y_dist = (y0 - y1).abs();
-        vec![
-            (x0 + x_dist, y0 + y_dist),
-            (x0 - x_dist, y0 + y_dist),
-            (x0 + x_dist, y0 - y_dist),
-            (x0 - x_dist, y0 - y_dist),
-            (x1 + x_dist, y1 + y_dist),
-            (x1 - x_dist, y1 + y_dist),
-            (x1 + x_dist, y1 - y_dist),
-            (x1 - x_dist, y1 - y_dist),
-        ]
+    fn step(a: Point, b: Point) -> (isize, isize) {
+        (b.0 as isize - a.0 as isize, b.1 as isize - a.1 as isize)
     }
 
-    fn right_dist(dist: usize, test: (usize, usize), a: (usize, usize), b: (usize, usize)) -> bool {
-        (Self::dist(a, test) == dist && Self::dist(b, test) == dist * 2)
-            || (Self::dist(a, test) == dist * 2 && Self::dist(b, test) == dist)
+    fn offset(&self, loc: Point, step: (isize, isize)) -> Option<Point> {
+        let x = loc.0 as isize + step.0;
+        let y = loc.1 as isize + step.1;
+        if self.in_grid(x, y) {
+            Some((x as usize, y as usize))
+        } else {
+            None
+        }
+    }
+
+    fn ray(&self, start: Point, step: (isize, isize)) -> Vec<Point> {
+        let mut res = vec![start];
+        let mut current = start;
+        while let Some(next) = self.offset(current, step) {
+            res.push(next);
+            current = next;
+        }
+        res
     }
 
     fn in_grid(&self, x: isize, y: isize) -> bool {
         x >= 0 && y >= 0 && x < self.hwidth as isize && y < self.vwidth as isize
     }
 
-    fn calculate_antinodes(&self, a: (usize, usize), b: (usize, usize)) -> Vec<(usize, usize)> {
-        let mut res = Vec::new();
-        let dist = Self::dist(a, b);
-        for possibility in self.points(a, b) {
-            if self.in_grid(possibility.0, possibility.1) {
-                let local_x = possibility.0 as usize;
-                let local_y = possibility.1 as usize;
-                if Self::right_dist(dist, (local_x, local_y), a, b) {
-                    res.push((local_x, local_y));
-                }
-            }
-        }
-        res
+    fn antennas_of(&self, antenna: char) -> &[Point] {
+        self.antennas
+            .get(&antenna)
+            .map_or(&[], |locs| locs.as_slice())
     }
 
-    fn find_antinodes(&self, antenna: char) -> Vec<(usize, usize)> {
-        let mut locs = Vec::new();
-        for (y, row) in self.grid.iter().enumerate() {
-            for (x, c) in row.iter().enumerate() {
-                if *c == antenna {
-                    locs.push((x, y));
-                }
-            }
-        }
-        let mut results = Vec::new();
-        for pair in locs.iter().combinations(2) {
-            results.push(self.calculate_antinodes(*pair[0], *pair[1]));
-        }
-        results
+    fn frequencies_sharing(&self, shared: impl Fn(&Point) -> bool) -> Vec<char> {
+        self.antennas
+            .iter()
+            .filter(|(_, locs)| locs.iter().filter(|loc| shared(loc)).count() > 1)
+            .map(|(&antenna, _)| antenna)
+            .sorted()
+            .collect()
+    }
+
+    fn frequencies_sharing_row(&self, y: usize) -> Vec<char> {
+        self.frequencies_sharing(|loc| loc.1 == y)
+    }
+
+    fn frequencies_sharing_column(&self, x: usize) -> Vec<char> {
+        self.frequencies_sharing(|loc| loc.0 == x)
+    }
+
+    fn calculate_antinodes(&self, a: Point, b: Point) -> Vec<Point> {
+        let (dx, dy) = Self::step(a, b);
+        [self.offset(a, (-dx, -dy)), self.offset(b, (dx, dy))]
             .into_iter()
             .flatten()
-            .filter(|item| !locs.contains(item))
             .collect()
     }
 
-    fn antinodes(&self) -> HashSet<(usize, usize)> {
-        self.antenna
-            .clone()
-            .into_par_iter()
-            .map(|antenna| self.find_antinodes(antenna))
-            .flatten()
+    fn find_antinodes(&self, antenna: char) -> Vec<Point> {
+        self.antennas_of(antenna)
+            .iter()
+            .tuple_combinations()
+            .flat_map(|(a, b)| self.calculate_antinodes(*a, *b))
             .collect()
     }
 
+    fn antinodes(&self) -> HashSet<Point> {
+        self.antennas
+            .par_iter()
+            .map(|(&antenna, _)| self.find_antinodes(antenna))
+            .flatten()
+            .collect()
+    }
 
-    fn all_points(&self, a: (usize, usize), b: (usize, usize)) -> Vec<(isize, isize)> {
-        let x0 = a.0 as isize;
-        let x1 = b.0 as isize;
-        let y0 = a.1 as isize;
-        let y1 = b.1 as isize;
-        let x_diff = x0 - x1;
-        let y_diff = y0 - y1;
-        if x_diff == 0 && y_diff == 0 {
-            vec![]
-        } else if x_diff == 0 {
-            let mut res = Vec::new();
-            let mut high_y = max(y0, y1);
-            while high_y < self.vwidth as isize {
-                res.push((x0, high_y));
-                high_y += y_diff.abs();
-            }
-            let mut low_y = min(y0, y1);
-            while low_y >= 0 {
-                res.push((x0, low_y));
-                low_y -= y_diff.abs();
-            }
-            res
-        } else if y_diff == 0 {
-            let mut res = Vec::new();
-            let mut high_x = max(x0, x1);
-            while high_x < self.hwidth as isize {
-                res.push((high_x, y0));
-                high_x += x_diff.abs();
-            }
-            let mut low_x = min(x0, x1);
-            while low_x >= 0 {
-                res.push((low_x, y0));
-                low_x -= x_diff.abs();
-            }
-            res
-        } else {
-            let mut res = Vec::new();
-            let x_diff = x_diff.abs();
-            let y_diff = y_diff.abs();
-            if y0 > y1 {
-                if x0 > x1 {
-                    let mut next_point = (x0 + x_diff, y0 + y_diff);
-                    while self.in_grid(next_point.0, next_point.1) {
-                        res.push((next_point.0, next_point.1));
-                        next_point = (next_point.0 + x_diff, next_point.1 + y_diff);
-                    }
-                    let mut next_point = (x1 - x_diff, y1 - y_diff);
-                    while self.in_grid(next_point.0, next_point.1) {
-                        res.push((next_point.0, next_point.1));
-                        next_point = (next_point.0 - x_diff, next_point.1 - y_diff);
-                    }
-                } else {
-                    let mut next_point = (x0 - x_diff, y0 + y_diff);
-                    while self.in_grid(next_point.0, next_point.1) {
-                        res.push((next_point.0, next_point.1));
-                        next_point = (next_point.0 - x_diff, next_point.1 + y_diff);
-                    }
-                    let mut next_point = (x1 + x_diff, y1 - y_diff);
-                    while self.in_grid(next_point.0, next_point.1) {
-                        res.push((next_point.0, next_point.1));
-                        next_point = (next_point.0 + x_diff, next_point.1 - y_diff);
-                    }
-                }
-            } else {
-                if x0 > x1 {
-                    let mut next_point = (x0 + x_diff, y0 - y_diff);
-                    while self.in_grid(next_point.0, next_point.1) {
-                        res.push((next_point.0, next_point.1));
-                        next_point = (next_point.0 + x_diff, next_point.1 - y_diff);
-                    }
-                    let mut next_point = (x1 - x_diff, y1 + y_diff);
-                    while self.in_grid(next_point.0, next_point.1) {
-                        res.push((next_point.0, next_point.1));
-                        next_point = (next_point.0 - x_diff, next_point.1 + y_diff);
-                    }
-                } else {
-                    let mut next_point = (x0 - x_diff, y0 - y_diff);
-                    while self.in_grid(next_point.0, next_point.1) {
-                        res.push((next_point.0, next_point.1));
-                        next_point = (next_point.0 - x_diff, next_point.1 - y_diff);
-                    }
-                    let mut next_point = (x1 + x_diff, y1 + y_diff);
-                    while self.in_grid(next_point.0, next_point.1) {
-                        res.push((next_point.0, next_point.1));
-                        next_point = (next_point.0 + x_diff, next_point.1 + y_diff);
-                    }
-                }
-            }
-            res
+    fn calculate_all_antinodes(&self, a: Point, b: Point) -> Vec<Point> {
+        let (dx, dy) = Self::step(a, b);
+        let divisor = Self::gcd(dx, dy);
+        if divisor == 0 {
+            return vec![];
         }
+        let (dx, dy) = (dx / divisor, dy / divisor);
+        let mut res = self.ray(a, (dx, dy));
+        res.extend(self.ray(a, (-dx, -dy)).into_iter().skip(1));
+        res
     }
 
-    fn calculate_all_antinodes(&self, a: (usize, usize), b: (usize, usize)) -> Vec<(usize, usize)> {
-        let mut res = Vec::new();
-        for possibility in self.all_points(a, b) {
-            if self.in_grid(possibility.0, possibility.1) {
-                let local_x = possibility.0 as usize;
-                let local_y = possibility.1 as usize;
-                res.push((local_x, local_y));
-            }
+    fn find_all_antinodes(&self, antenna: char) -> Vec<Point> {
+        self.antennas_of(antenna)
+            .iter()
+            .tuple_combinations()
+            .flat_map(|(a, b)| self.calculate_all_antinodes(*a, *b))
+            .collect()
+    }
+
+    fn extended_antinodes(&self) -> HashSet<Point> {
+        self.antennas
+            .par_iter()
+            .map(|(&antenna, _)| self.find_all_antinodes(antenna))
+            .flatten()
+            .collect()
+    }
+
+    fn frequency_antinodes(&self, antenna: char, extended: bool) -> Vec<Point> {
+        if extended {
+            self.find_all_antinodes(antenna)
+        } else {
+            self.find_antinodes(antenna)
         }
-        res
     }
 
-    fn find_all_antinodes(&self, antenna: char) -> Vec<(usize, usize)> {
-        let mut locs = Vec::new();
+    fn render(&self, frequency: Option<char>, extended: bool) -> String {
+        let frequencies = match frequency {
+            Some(antenna) => vec![antenna],
+            None => self.antennas.keys().copied().collect(),
+        };
+        let nodes: HashSet<Point> = frequencies
+            .into_iter()
+            .flat_map(|antenna| self.frequency_antinodes(antenna, extended))
+            .collect();
+        let mut res = String::new();
         for (y, row) in self.grid.iter().enumerate() {
-            for (x, c) in row.iter().enumerate() {
-                if *c == antenna {
-                    locs.push((x, y));
+            for (x, &c) in row.iter().enumerate() {
+                if c != '.' && frequency.is_none_or(|antenna| antenna == c) {
+                    res.push(c);
+                } else if nodes.contains(&(x, y)) {
+                    res.push('#');
+                } else {
+                    res.push('.');
                 }
             }
+            res.push('\n');
         }
-        let mut results = Vec::new();
-        for pair in locs.iter().combinations(2) {
-            results.push(self.calculate_all_antinodes(*pair[0], *pair[1]));
-        }
-        results
-            .into_iter()
-            .flatten()
-            .filter(|item| !locs.contains(item))
-            .collect()
+        res
     }
 
-    fn extended_antinodes(&self) -> HashSet<(usize, usize)> {
-        let mut set: HashSet<(usize, usize)> = self.antenna
-            .clone()
-            .into_par_iter()
-            .map(|antenna| self.find_all_antinodes(antenna))
-            .flatten()
-            .collect();
-            for loc in &self.antenna_locs {
-                set.insert(loc.clone());
-            }
-        set
+    fn summary(&self, extended: bool) -> Vec<(char, usize)> {
+        self.antennas
+            .keys()
+            .sorted()
+            .map(|&antenna| {
+                let nodes: HashSet<Point> = self
+                    .frequency_antinodes(antenna, extended)
+                    .into_iter()
+                    .collect();
+                (antenna, nodes.len())
+            })
+            .collect()
     }
 }
 
@@ -237,13 +182,11 @@ impl From<&str> for Map {
         for line in input.lines() {
             grid.push(line.chars().collect::<Vec<char>>());
         }
-        let mut antenna = HashSet::new();
-        let mut antenna_locs = Vec::new();
+        let mut antennas: HashMap<char, Vec<Point>> = HashMap::new();
         for (y, row) in grid.iter().enumerate() {
             for (x, c) in row.iter().enumerate() {
                 if *c != '.' {
-                    antenna.insert(*c);
-                    antenna_locs.push((x, y));
+                    antennas.entry(*c).or_default().push((x, y));
                 }
             }
         }
@@ -251,8 +194,7 @@ impl From<&str> for Map {
             hwidth,
             vwidth,
             grid,
-            antenna,
-            antenna_locs,
+            antennas,
         }
     }
 }
@@ -269,6 +211,34 @@ fn part2(input: &str) -> usize {
 
 fn main() {
     let input = fs::read_to_string("input.txt").expect("input.txt should exist");
+    let args: Vec<String> = env::args().collect();
+    if args.iter().any(|arg| arg == "--render") {
+        let map = Map::from(&*input);
+        let extended = args.iter().any(|arg| arg == "--extended");
+        let frequency = args
+            .iter()
+            .position(|arg| arg == "--frequency")
+            .and_then(|idx| args.get(idx + 1))
+            .and_then(|arg| arg.chars().next());
+        print!("{}", map.render(frequency, extended));
+        for (antenna, count) in map.summary(extended) {
+            if frequency.is_none_or(|f| f == antenna) {
+                println!("{}: {}", antenna, count);
+            }
+        }
+        for y in 0..map.vwidth {
+            let shared = map.frequencies_sharing_row(y);
+            if !shared.is_empty() {
+                println!("row {}: {}", y, shared.iter().join(" "));
+            }
+        }
+        for x in 0..map.hwidth {
+            let shared = map.frequencies_sharing_column(x);
+            if !shared.is_empty() {
+                println!("column {}: {}", x, shared.iter().join(" "));
+            }
+        }
+    }
     println!("{}", part1(&input));
     println!("{}", part2(&input));
 }
@@ -288,4 +258,63 @@ mod tests {
         let input = fs::read_to_string("test.txt").expect("test.txt should exist");
         assert_eq!(34, part2(&input));
     }
+
+    #[test]
+    fn test_harmonics() {
+        let input = "T.........\n...T......\n.T........\n..........\n..........\n..........\n..........\n..........\n..........\n..........\n";
+        assert_eq!(9, part2(input));
+        let map = Map::from("..A.A..\n");
+        assert_eq!(7, map.extended_antinodes().len());
+        assert_eq!(2, map.antinodes().len());
+    }
+
+    #[test]
+    fn test_render() {
+        let input = fs::read_to_string("test.txt").expect("test.txt should exist");
+        let map = Map::from(&*input);
+        let expected = "\
+......#....#
+...#....0...
+....#0....#.
+..#....0....
+....0....#..
+.#....A.....
+...#........
+#......#....
+........A...
+.........A..
+..........#.
+..........#.
+";
+        assert_eq!(expected, map.render(None, false));
+        let map = Map::from("T.........\n...T......\n.T........\n..........\n..........\n..........\n..........\n..........\n..........\n..........\n");
+        let expected = "\
+T....#....
+...T......
+.T....#...
+.........#
+..#.......
+..........
+...#......
+..........
+....#.....
+..........
+";
+        assert_eq!(expected, map.render(Some('T'), true));
+        assert_eq!(vec![('T', 9)], map.summary(true));
+    }
+
+    #[test]
+    fn test_index() {
+        let input = fs::read_to_string("test.txt").expect("test.txt should exist");
+        let map = Map::from(&*input);
+        assert_eq!(&[(8, 1), (5, 2), (7, 3), (4, 4)], map.antennas_of('0'));
+        assert_eq!(&[(6, 5), (8, 8), (9, 9)], map.antennas_of('A'));
+        assert!(map.antennas_of('T').is_empty());
+        let map = Map::from("a..a\nb..c\nb..c\n");
+        assert_eq!(vec!['a'], map.frequencies_sharing_row(0));
+        assert!(map.frequencies_sharing_row(1).is_empty());
+        assert_eq!(vec!['b'], map.frequencies_sharing_column(0));
+        assert_eq!(vec!['c'], map.frequencies_sharing_column(3));
+    }
 }
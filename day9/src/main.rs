@@ -1,102 +1,508 @@
+use std::cmp::{min, Reverse};
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap, HashMap};
+use std::env;
+use std::fmt;
 use std::fs;
+use std::ops::Range;
+
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+struct Span {
+    start: usize,
+    len: usize,
+}
+
+impl Span {
+    fn new(start: usize, len: usize) -> Self {
+        Self { start, len }
+    }
+
+    fn end(&self) -> usize {
+        self.start + self.len
+    }
+}
 
 #[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
 struct File {
     id: usize,
-    len: usize,
+    span: Span,
 }
 
 impl File {
-    fn new(id: usize, len: usize) -> Self {
-        Self { id, len }
+    fn new(id: usize, start: usize, len: usize) -> Self {
+        Self {
+            id,
+            span: Span::new(start, len),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Hash, Eq, PartialEq)]
+struct Move {
+    id: usize,
+    from: Range<usize>,
+    to: Range<usize>,
+}
+
+impl Move {
+    fn new(id: usize, from: usize, to: usize, len: usize) -> Self {
+        Self {
+            id,
+            from: from..from + len,
+            to: to..to + len,
+        }
+    }
+}
+
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "file {}: {}..{} -> {}..{}",
+            self.id, self.from.start, self.from.end, self.to.start, self.to.end
+        )
+    }
+}
+
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+enum CompactionPolicy {
+    Blockwise,
+    FirstFit,
+    BestFit,
+    WorstFit,
+    Pack,
+}
+
+impl CompactionPolicy {
+    const ALL: [CompactionPolicy; 5] = [
+        CompactionPolicy::Blockwise,
+        CompactionPolicy::FirstFit,
+        CompactionPolicy::BestFit,
+        CompactionPolicy::WorstFit,
+        CompactionPolicy::Pack,
+    ];
+
+    fn compact(&self, disk_map: &DiskMap) -> (DiskMap, Vec<Move>) {
+        match self {
+            CompactionPolicy::Blockwise => disk_map.defrag_traced(),
+            CompactionPolicy::Pack => disk_map.pack_traced(),
+            _ => disk_map.fit_traced(*self),
+        }
+    }
+
+    fn report(&self, disk_map: &DiskMap) -> Report {
+        let (compacted, moves) = self.compact(disk_map);
+        Report {
+            policy: *self,
+            checksum: compacted.checksum(),
+            moves: moves.len(),
+            fragmentation: compacted.fragmentation(),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+struct Report {
+    policy: CompactionPolicy,
+    checksum: usize,
+    moves: usize,
+    fragmentation: usize,
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:?}: checksum {}, {} moves, fragmentation {}",
+            self.policy, self.checksum, self.moves, self.fragmentation
+        )
     }
 }
 
 #[derive(Clone, Debug, Hash, Eq, PartialEq)]
 struct DiskMap {
     files: Vec<File>,
-    map: Vec<Option<usize>>,
+    free: Vec<Span>,
+    size: usize,
 }
 
 impl From<&str> for DiskMap {
     fn from(s: &str) -> Self {
-        let mut file_id = 0;
-        let mut is_file = true;
         let mut files = Vec::new();
-        let mut map = Vec::new();
-        for c in s.chars() {
+        let mut free = Vec::new();
+        let mut pos = 0;
+        for (idx, c) in s.trim().chars().enumerate() {
             let number = c.to_digit(10).unwrap() as usize;
-            if is_file {
-                files.push(File::new(file_id, number));
-                for _ in 0..number {
-                    map.push(Some(file_id));
-                }
-                file_id += 1;
-                is_file = false;
-            } else {
-                for _ in 0..number {
-                    map.push(None);
-                }
-                is_file = true;
+            if idx % 2 == 0 {
+                files.push(File::new(idx / 2, pos, number));
+            } else if number > 0 {
+                free.push(Span::new(pos, number));
             }
+            pos += number;
+        }
+        DiskMap {
+            files,
+            free,
+            size: pos,
         }
-        DiskMap { files, map }
     }
 }
 
 impl DiskMap {
+    fn with_files(mut files: Vec<File>, size: usize) -> DiskMap {
+        files.sort_by_key(|file| (file.span.start, file.id));
+        let mut merged: Vec<File> = Vec::with_capacity(files.len());
+        for file in files {
+            match merged.last_mut() {
+                Some(last) if last.id == file.id && last.span.end() == file.span.start => {
+                    last.span.len += file.span.len;
+                }
+                _ => merged.push(file),
+            }
+        }
+        let mut free = Vec::new();
+        let mut cursor = 0;
+        for file in &merged {
+            if file.span.start > cursor {
+                free.push(Span::new(cursor, file.span.start - cursor));
+            }
+            cursor = cursor.max(file.span.end());
+        }
+        if size > cursor {
+            free.push(Span::new(cursor, size - cursor));
+        }
+        DiskMap {
+            files: merged,
+            free,
+            size,
+        }
+    }
+
+    fn blocks(&self) -> Vec<Option<usize>> {
+        let mut map = vec![None; self.size];
+        for file in &self.files {
+            for block in &mut map[file.span.start..file.span.end()] {
+                *block = Some(file.id);
+            }
+        }
+        map
+    }
+
+    fn from_blocks(s: &str) -> DiskMap {
+        let mut files: Vec<File> = Vec::new();
+        let blocks = s.trim();
+        for (idx, c) in blocks.chars().enumerate() {
+            if let Some(id) = c.to_digit(10) {
+                let id = id as usize;
+                match files.last_mut() {
+                    Some(last) if last.id == id && last.span.end() == idx => last.span.len += 1,
+                    _ => files.push(File::new(id, idx, 1)),
+                }
+            }
+        }
+        DiskMap::with_files(files, blocks.len())
+    }
+
+    // The dense format can only describe files laid out in id order with
+    // every file and gap at most 9 blocks long, so most compacted maps (and
+    // any map with a longer run) have no dense encoding and give `None`.
+    fn encode_dense(&self) -> Option<String> {
+        let mut res = String::new();
+        let mut cursor = 0;
+        for (idx, file) in self.files.iter().enumerate() {
+            if file.id != idx {
+                return None;
+            }
+            if idx > 0 {
+                res.push(char::from_digit((file.span.start - cursor) as u32, 10)?);
+            } else if file.span.start != 0 {
+                return None;
+            }
+            res.push(char::from_digit(file.span.len as u32, 10)?);
+            cursor = file.span.end();
+        }
+        if self.size > cursor {
+            res.push(char::from_digit((self.size - cursor) as u32, 10)?);
+        }
+        Some(res)
+    }
+
+    fn encode_blocks(&self) -> Option<String> {
+        self.blocks()
+            .into_iter()
+            .map(|block| match block {
+                Some(id) => char::from_digit(id as u32, 10),
+                None => Some('.'),
+            })
+            .collect()
+    }
+
     fn defrag(&self) -> DiskMap {
-        let mut new_map = self.map.clone();
-        'outer: for i in (0..new_map.len()).rev() {
-            if let Some(&item) = new_map.get(i) {
-                if new_map[..i].iter().any(|x| x.is_none()) {
-                    for j in 0..i {
-                        if new_map[j].is_none() {
-                            new_map[j] = item;
-                            new_map[i] = None;
-                            continue 'outer;
+        self.defrag_traced().0
+    }
+
+    fn defrag_traced(&self) -> (DiskMap, Vec<Move>) {
+        let mut gaps = self.free.iter().copied();
+        let mut gap = gaps.next();
+        let mut files = Vec::new();
+        let mut moves = Vec::new();
+        for file in self.files.iter().rev() {
+            let mut remaining = file.span.len;
+            while remaining > 0 {
+                match gap.as_mut() {
+                    Some(current) if current.start < file.span.start => {
+                        let take = min(current.len, remaining);
+                        let from = file.span.start + remaining - take;
+                        files.push(File::new(file.id, current.start, take));
+                        moves.push(Move::new(file.id, from, current.start, take));
+                        remaining -= take;
+                        current.start += take;
+                        current.len -= take;
+                        if current.len == 0 {
+                            gap = gaps.next();
                         }
                     }
+                    _ => break,
                 }
             }
+            if remaining > 0 {
+                files.push(File::new(file.id, file.span.start, remaining));
+            }
         }
-        DiskMap {
-            files: self.files.clone(),
-            map: new_map,
-        }
+        (DiskMap::with_files(files, self.size), moves)
     }
 
     fn defrag_contiguous(&self) -> DiskMap {
-        let mut new_map = self.map.clone();
-        'outer: for file in self.files.iter().rev() {
-            let len = file.len;
-            let mut current_len = 0;
-            for i in 0..new_map.len() {
-                if new_map[i].is_some() {
-                    current_len = 0;
-                } else {
-                    current_len += 1;
-                    if current_len >= len {
-                        let old_loc = new_map.iter().position(|x| x.is_some() && x.unwrap() == file.id).unwrap();
-                        let new_loc = i-current_len+1;
-                        if old_loc > new_loc {
-                            new_map.splice(old_loc..old_loc+len, vec![None; len]);
-                            new_map.splice(new_loc..new_loc+len, vec![Some(file.id); len]);
+        self.fit_traced(CompactionPolicy::FirstFit).0
+    }
+
+    // Releases a vacated span back into the free map, merged with any free
+    // space either side, and files the merged gap under its new length.
+    fn release(
+        free: &mut BTreeMap<usize, usize>,
+        buckets: &mut BTreeMap<usize, BinaryHeap<Reverse<usize>>>,
+        span: Span,
+    ) {
+        if span.len == 0 {
+            return;
+        }
+        let (mut start, mut end) = (span.start, span.end());
+        if let Some((&prev, &len)) = free.range(..start).next_back() {
+            if prev + len == start {
+                free.remove(&prev);
+                start = prev;
+            }
+        }
+        if let Some(len) = free.remove(&end) {
+            end += len;
+        }
+        free.insert(start, end - start);
+        buckets.entry(end - start).or_default().push(Reverse(start));
+    }
+
+    fn fit_traced(&self, policy: CompactionPolicy) -> (DiskMap, Vec<Move>) {
+        // Free space keyed by start. A bucket entry goes stale once its start
+        // no longer maps to a gap of that length and is dropped when it
+        // surfaces, so merged gaps only need a fresh entry.
+        let mut free: BTreeMap<usize, usize> =
+            self.free.iter().map(|gap| (gap.start, gap.len)).collect();
+        let mut buckets: BTreeMap<usize, BinaryHeap<Reverse<usize>>> = BTreeMap::new();
+        for gap in &self.free {
+            buckets.entry(gap.len).or_default().push(Reverse(gap.start));
+        }
+        let mut order = self.files.clone();
+        order.sort_by_key(|file| Reverse(file.id));
+        let mut files = Vec::new();
+        let mut moves = Vec::new();
+        for file in order {
+            let len = file.span.len;
+            let mut candidates = Vec::new();
+            for (&size, bucket) in buckets.range_mut(len.max(1)..) {
+                while let Some(&Reverse(start)) = bucket.peek() {
+                    if free.get(&start) == Some(&size) {
+                        if start < file.span.start {
+                            candidates.push((start, size));
                         }
-                        continue 'outer;
+                        break;
+                    }
+                    bucket.pop();
+                }
+            }
+            let candidates = candidates.into_iter();
+            let target = match policy {
+                CompactionPolicy::BestFit => candidates.min_by_key(|&(start, size)| (size, start)),
+                CompactionPolicy::WorstFit => {
+                    candidates.min_by_key(|&(start, size)| (Reverse(size), start))
+                }
+                _ => candidates.min(),
+            };
+            match target {
+                Some((start, size)) => {
+                    free.remove(&start);
+                    if size > len {
+                        free.insert(start + len, size - len);
+                        buckets
+                            .entry(size - len)
+                            .or_default()
+                            .push(Reverse(start + len));
                     }
+                    Self::release(&mut free, &mut buckets, file.span);
+                    files.push(File::new(file.id, start, len));
+                    moves.push(Move::new(file.id, file.span.start, start, len));
                 }
+                None => files.push(file),
             }
         }
-        DiskMap {
-            files: self.files.clone(),
-            map: new_map,
+        (DiskMap::with_files(files, self.size), moves)
+    }
+
+    fn shift(
+        blocks: &mut [Option<usize>],
+        free: &mut BTreeSet<usize>,
+        owned: &mut HashMap<usize, BTreeSet<usize>>,
+        moves: &mut Vec<Move>,
+        id: usize,
+        from: usize,
+        to: usize,
+    ) {
+        blocks[from] = None;
+        blocks[to] = Some(id);
+        free.remove(&to);
+        free.insert(from);
+        let positions = owned.entry(id).or_default();
+        positions.remove(&from);
+        positions.insert(to);
+        match moves.last_mut() {
+            Some(last) if last.id == id && last.from.end == from && last.to.end == to => {
+                last.from.end += 1;
+                last.to.end += 1;
+            }
+            _ => moves.push(Move::new(id, from, to, 1)),
+        }
+    }
+
+    fn pack_traced(&self) -> (DiskMap, Vec<Move>) {
+        // Files are laid out back to back in order of first appearance, each
+        // as one extent. Blocks of a later file sitting where the current
+        // file belongs are moved out to free space first, so a full disk
+        // with nowhere to shuffle through is left as it is.
+        let mut blocks = self.blocks();
+        let mut free: BTreeSet<usize> = blocks
+            .iter()
+            .enumerate()
+            .filter(|(_, block)| block.is_none())
+            .map(|(idx, _)| idx)
+            .collect();
+        if free.is_empty() {
+            return (self.clone(), Vec::new());
+        }
+        let mut owned: HashMap<usize, BTreeSet<usize>> = HashMap::new();
+        let mut layout = Vec::new();
+        for (idx, block) in blocks.iter().enumerate() {
+            if let Some(id) = *block {
+                let positions = owned.entry(id).or_default();
+                if positions.is_empty() {
+                    layout.push(id);
+                }
+                positions.insert(idx);
+            }
+        }
+        let mut moves = Vec::new();
+        let mut cursor = 0;
+        for id in layout {
+            let len = owned[&id].len();
+            for target in cursor..cursor + len {
+                if blocks[target] == Some(id) {
+                    continue;
+                }
+                if let Some(other) = blocks[target] {
+                    let spare = *free
+                        .range(cursor + len..)
+                        .next()
+                        .or_else(|| free.range(target..).next())
+                        .unwrap();
+                    Self::shift(
+                        &mut blocks,
+                        &mut free,
+                        &mut owned,
+                        &mut moves,
+                        other,
+                        target,
+                        spare,
+                    );
+                }
+                let source = *owned[&id].range(target..).next().unwrap();
+                Self::shift(
+                    &mut blocks,
+                    &mut free,
+                    &mut owned,
+                    &mut moves,
+                    id,
+                    source,
+                    target,
+                );
+            }
+            cursor += len;
+        }
+        let files = blocks
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, block)| block.map(|id| File::new(id, idx, 1)))
+            .collect();
+        (DiskMap::with_files(files, self.size), moves)
+    }
+
+    // Counts the gaps left between files plus every extra extent of a file
+    // that is split across the disk.
+    fn fragmentation(&self) -> usize {
+        let end = self
+            .files
+            .iter()
+            .map(|file| file.span.end())
+            .max()
+            .unwrap_or(0);
+        let ids: BTreeSet<usize> = self.files.iter().map(|file| file.id).collect();
+        self.free.iter().filter(|gap| gap.start < end).count() + self.files.len() - ids.len()
+    }
+
+    fn apply(&self, step: &Move) -> DiskMap {
+        let mut files = Vec::new();
+        for file in &self.files {
+            if file.id == step.id
+                && file.span.start < step.from.end
+                && step.from.start < file.span.end()
+            {
+                if file.span.start < step.from.start {
+                    files.push(File::new(
+                        file.id,
+                        file.span.start,
+                        step.from.start - file.span.start,
+                    ));
+                }
+                if file.span.end() > step.from.end {
+                    files.push(File::new(
+                        file.id,
+                        step.from.end,
+                        file.span.end() - step.from.end,
+                    ));
+                }
+            } else {
+                files.push(*file);
+            }
         }
+        files.push(File::new(step.id, step.to.start, step.to.len()));
+        DiskMap::with_files(files, self.size)
+    }
+
+    fn replay<'a>(&self, moves: &'a [Move]) -> impl Iterator<Item = (&'a Move, DiskMap)> + 'a {
+        moves.iter().scan(self.clone(), |state, step| {
+            *state = state.apply(step);
+            Some((step, state.clone()))
+        })
     }
 
     fn checksum(&self) -> usize {
         let mut sum = 0;
-        for (idx, &item) in self.map.iter().enumerate() {
+        for (idx, &item) in self.blocks().iter().enumerate() {
             if let Some(id) = item {
                 sum += idx * id;
             }
@@ -117,8 +523,54 @@ fn part2(input: &str) -> usize {
     disk_map.checksum()
 }
 
+fn trace(disk_map: &DiskMap, moves: &[Move]) {
+    if let Some(blocks) = disk_map.encode_blocks() {
+        println!("{}", blocks);
+    }
+    for (step, state) in disk_map.replay(moves) {
+        println!("{}", step);
+        if let Some(blocks) = state.encode_blocks() {
+            println!("{}", blocks);
+        }
+    }
+}
+
 fn main() {
     let input = fs::read_to_string("input.txt").expect("input.txt should exist");
+    let args: Vec<String> = env::args().collect();
+    let snapshot = args.iter().any(|arg| arg == "--blocks");
+    let disk_map = if snapshot {
+        DiskMap::from_blocks(&input)
+    } else {
+        DiskMap::from(&*input)
+    };
+    if args.iter().any(|arg| arg == "--trace") {
+        for policy in [CompactionPolicy::Blockwise, CompactionPolicy::FirstFit] {
+            trace(&disk_map, &policy.compact(&disk_map).1);
+        }
+    }
+    if args.iter().any(|arg| arg == "--policies") {
+        for policy in CompactionPolicy::ALL {
+            println!("{}", policy.report(&disk_map));
+        }
+    }
+    if args.iter().any(|arg| arg == "--snapshot") {
+        for policy in CompactionPolicy::ALL {
+            let compacted = policy.compact(&disk_map).0;
+            println!(
+                "{:?}: {} (dense: {})",
+                policy,
+                compacted.encode_blocks().unwrap_or("-".to_string()),
+                compacted.encode_dense().unwrap_or("-".to_string())
+            );
+        }
+    }
+    if snapshot {
+        for policy in [CompactionPolicy::Blockwise, CompactionPolicy::FirstFit] {
+            println!("{}", policy.compact(&disk_map).0.checksum());
+        }
+        return;
+    }
     println!("{}", part1(&input));
     println!("{}", part2(&input));
 }
@@ -138,4 +590,134 @@ mod tests {
         let input = fs::read_to_string("test.txt").expect("test.txt should exist");
         assert_eq!(2858, part2(&input));
     }
+
+    #[test]
+    fn test_spans() {
+        let disk_map = DiskMap::from("12345").defrag();
+        assert_eq!(
+            vec![
+                File::new(0, 0, 1),
+                File::new(2, 1, 2),
+                File::new(1, 3, 3),
+                File::new(2, 6, 3)
+            ],
+            disk_map.files
+        );
+        assert_eq!(vec![Span::new(9, 6)], disk_map.free);
+        assert_eq!(15, disk_map.blocks().len());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let input = fs::read_to_string("test.txt").expect("test.txt should exist");
+        let disk_map = DiskMap::from(&*input);
+        assert_eq!(Some(input.trim().to_string()), disk_map.encode_dense());
+        let blocks = disk_map.encode_blocks().unwrap();
+        assert_eq!("00...111...2...333.44.5555.6666.777.888899", blocks);
+        assert_eq!(disk_map, DiskMap::from_blocks(&blocks));
+
+        let compacted = disk_map.defrag();
+        let blocks = compacted.encode_blocks().unwrap();
+        assert_eq!("0099811188827773336446555566..............", blocks);
+        assert_eq!(compacted, DiskMap::from_blocks(&blocks));
+        assert_eq!(None, compacted.encode_dense());
+
+        let compacted = disk_map.defrag_contiguous();
+        let blocks = compacted.encode_blocks().unwrap();
+        assert_eq!("00992111777.44.333....5555.6666.....8888..", blocks);
+        assert_eq!(compacted, DiskMap::from_blocks(&blocks));
+
+        let disk_map = DiskMap::from("12345");
+        assert_eq!("0..111....22222", disk_map.encode_blocks().unwrap());
+        assert_eq!(Some("12345".to_string()), disk_map.encode_dense());
+        let compacted = DiskMap::from_blocks("012......");
+        assert_eq!(Some("101016".to_string()), compacted.encode_dense());
+    }
+
+    #[test]
+    fn test_trace() {
+        let disk_map = DiskMap::from("12345");
+        let (compacted, moves) = disk_map.defrag_traced();
+        let steps: Vec<String> = disk_map
+            .replay(&moves)
+            .map(|(_, state)| state.encode_blocks().unwrap())
+            .collect();
+        assert_eq!(vec!["022111....222..", "022111222......"], steps);
+        assert_eq!(Move::new(2, 13, 1, 2), moves[0]);
+        assert_eq!(compacted, disk_map.replay(&moves).last().unwrap().1);
+
+        let input = fs::read_to_string("test.txt").expect("test.txt should exist");
+        let disk_map = DiskMap::from(&*input);
+        let (compacted, moves) = CompactionPolicy::FirstFit.compact(&disk_map);
+        let steps: Vec<String> = disk_map
+            .replay(&moves)
+            .map(|(step, state)| format!("{} {}", step, state.encode_blocks().unwrap()))
+            .collect();
+        assert_eq!(
+            vec![
+                "file 9: 40..42 -> 2..4 0099.111...2...333.44.5555.6666.777.8888..",
+                "file 7: 32..35 -> 8..11 0099.1117772...333.44.5555.6666.....8888..",
+                "file 4: 19..21 -> 12..14 0099.111777244.333....5555.6666.....8888..",
+                "file 2: 11..12 -> 4..5 00992111777.44.333....5555.6666.....8888.."
+            ],
+            steps
+        );
+        assert_eq!(compacted, disk_map.replay(&moves).last().unwrap().1);
+    }
+
+    #[test]
+    fn test_policies() {
+        let disk_map = DiskMap::from_blocks("0...1.2.");
+        let compacted =
+            |policy: CompactionPolicy| policy.compact(&disk_map).0.encode_blocks().unwrap();
+        assert_eq!("021.....", compacted(CompactionPolicy::FirstFit));
+        assert_eq!("01...2..", compacted(CompactionPolicy::BestFit));
+        assert_eq!("021.....", compacted(CompactionPolicy::WorstFit));
+        assert_eq!("012.....", compacted(CompactionPolicy::Pack));
+        assert_eq!("021.....", compacted(CompactionPolicy::Blockwise));
+
+        let disk_map = DiskMap::from_blocks("..22.111");
+        let compacted =
+            |policy: CompactionPolicy| policy.compact(&disk_map).0.encode_blocks().unwrap();
+        assert_eq!("22111...", compacted(CompactionPolicy::FirstFit));
+        assert_eq!("22111...", compacted(CompactionPolicy::BestFit));
+        let (compacted, moves) = CompactionPolicy::FirstFit.compact(&disk_map);
+        assert_eq!(compacted, disk_map.replay(&moves).last().unwrap().1);
+
+        let input = fs::read_to_string("test.txt").expect("test.txt should exist");
+        let disk_map = DiskMap::from(&*input);
+        let reports: Vec<Report> = CompactionPolicy::ALL
+            .iter()
+            .map(|policy| policy.report(&disk_map))
+            .collect();
+        assert_eq!(1928, reports[0].checksum);
+        assert_eq!(3, reports[0].fragmentation);
+        assert_eq!(2858, reports[1].checksum);
+        assert_eq!(4, reports[1].moves);
+        assert_eq!(5, reports[1].fragmentation);
+        assert_eq!(0, reports[4].fragmentation);
+        assert_eq!(
+            "0099811188827773336446555566..............",
+            disk_map.defrag().encode_blocks().unwrap()
+        );
+        for (blocks, expected) in [("0.1.0", "001.."), ("01.0", "001."), ("1.0.21.", "1102...")] {
+            let disk_map = DiskMap::from_blocks(blocks);
+            let (packed, moves) = CompactionPolicy::Pack.compact(&disk_map);
+            assert_eq!(expected, packed.encode_blocks().unwrap());
+            assert_eq!(0, packed.fragmentation());
+            assert_eq!(packed, disk_map.replay(&moves).last().unwrap().1);
+        }
+        assert_eq!(3, DiskMap::from_blocks("0.1.0").fragmentation());
+        let full = DiskMap::from_blocks("010");
+        assert_eq!(
+            (full.clone(), Vec::new()),
+            CompactionPolicy::Pack.compact(&full)
+        );
+        let (packed, moves) = CompactionPolicy::Pack.compact(&DiskMap::from("123"));
+        assert_eq!("0111..", packed.encode_blocks().unwrap());
+        assert_eq!(
+            packed,
+            DiskMap::from("123").replay(&moves).last().unwrap().1
+        );
+    }
 }